@@ -0,0 +1,961 @@
+use color::{AlphaColor, Srgb};
+use serde::Deserialize;
+use std::mem::discriminant;
+use std::time::Instant;
+use taffy::prelude::*;
+
+use crate::gui::BorderRadius;
+use crate::transform::{Affine, TransformFunction};
+
+/// A node property with the value it animates to
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimatedValue {
+    Opacity(f32),
+    Transform(Vec<TransformFunction>),
+    /// Gamma sRGB, like the colors of the nodes
+    BackgroundColor([f32; 4]),
+    /// Layout sizes in pixels, which the node keeps after the animation
+    /// until its props change the size
+    Width(f32),
+    Height(f32),
+    /// Top, right, bottom and left
+    BorderColors([[f32; 4]; 4]),
+    BorderRadius(BorderRadius),
+    Style(StyleValue),
+}
+
+impl AnimatedValue {
+    /// Whether the node needs a new layout when the value changes
+    pub fn affects_layout(&self) -> bool {
+        matches!(
+            self,
+            AnimatedValue::Width(_) | AnimatedValue::Height(_) | AnimatedValue::Style(_)
+        )
+    }
+
+    pub fn same_property(&self, other: &AnimatedValue) -> bool {
+        let is_width = |value: &AnimatedValue| {
+            matches!(
+                value,
+                AnimatedValue::Width(_) | AnimatedValue::Style(StyleValue::Width(_))
+            )
+        };
+        let is_height = |value: &AnimatedValue| {
+            matches!(
+                value,
+                AnimatedValue::Height(_) | AnimatedValue::Style(StyleValue::Height(_))
+            )
+        };
+
+        // sizes in pixels animate the width and height styles
+        if is_width(self) || is_height(self) {
+            return (is_width(self) && is_width(other)) || (is_height(self) && is_height(other));
+        }
+
+        match (self, other) {
+            (AnimatedValue::Style(style), AnimatedValue::Style(other)) => {
+                discriminant(style) == discriminant(other)
+            }
+            _ => discriminant(self) == discriminant(other),
+        }
+    }
+
+    /// `self` at 0 and `to` at 1, springs overshoot past both ends. `size`
+    /// resolves transforms that can only be mixed as matrices.
+    pub fn interpolate(&self, to: &AnimatedValue, progress: f32, size: Size<f32>) -> AnimatedValue {
+        match (self, to) {
+            (AnimatedValue::Opacity(from), AnimatedValue::Opacity(to)) => {
+                AnimatedValue::Opacity(lerp(*from, *to, progress))
+            }
+            (AnimatedValue::Transform(from), AnimatedValue::Transform(to)) => {
+                AnimatedValue::Transform(interpolate_transforms(from, to, progress, size))
+            }
+            (AnimatedValue::BackgroundColor(from), AnimatedValue::BackgroundColor(to)) => {
+                AnimatedValue::BackgroundColor(interpolate_color(*from, *to, progress))
+            }
+            (AnimatedValue::Width(from), AnimatedValue::Width(to)) => {
+                AnimatedValue::Width(lerp(*from, *to, progress).max(0.0))
+            }
+            (AnimatedValue::Height(from), AnimatedValue::Height(to)) => {
+                AnimatedValue::Height(lerp(*from, *to, progress).max(0.0))
+            }
+            (AnimatedValue::BorderColors(from), AnimatedValue::BorderColors(to)) => {
+                AnimatedValue::BorderColors(std::array::from_fn(|side| {
+                    interpolate_color(from[side], to[side], progress)
+                }))
+            }
+            (AnimatedValue::BorderRadius(from), AnimatedValue::BorderRadius(to)) => {
+                AnimatedValue::BorderRadius(from.interpolate(to, progress))
+            }
+            (AnimatedValue::Style(from), AnimatedValue::Style(to)) => {
+                AnimatedValue::Style(from.interpolate(to, progress))
+            }
+            _ => to.clone(),
+        }
+    }
+}
+
+/// The numeric taffy styles, which transition in their own units
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StyleValue {
+    Width(Dimension),
+    Height(Dimension),
+    MinWidth(Dimension),
+    MinHeight(Dimension),
+    MaxWidth(Dimension),
+    MaxHeight(Dimension),
+    FlexBasis(Dimension),
+    FlexGrow(f32),
+    FlexShrink(f32),
+    Margin(Rect<LengthPercentageAuto>),
+    Padding(Rect<LengthPercentage>),
+    BorderWidth(Rect<LengthPercentage>),
+    Inset(Rect<LengthPercentageAuto>),
+    Gap(Size<LengthPercentage>),
+}
+
+impl StyleValue {
+    /// The value `style` has for the same property
+    pub fn read(self, style: &Style) -> StyleValue {
+        match self {
+            StyleValue::Width(_) => StyleValue::Width(style.size.width),
+            StyleValue::Height(_) => StyleValue::Height(style.size.height),
+            StyleValue::MinWidth(_) => StyleValue::MinWidth(style.min_size.width),
+            StyleValue::MinHeight(_) => StyleValue::MinHeight(style.min_size.height),
+            StyleValue::MaxWidth(_) => StyleValue::MaxWidth(style.max_size.width),
+            StyleValue::MaxHeight(_) => StyleValue::MaxHeight(style.max_size.height),
+            StyleValue::FlexBasis(_) => StyleValue::FlexBasis(style.flex_basis),
+            StyleValue::FlexGrow(_) => StyleValue::FlexGrow(style.flex_grow),
+            StyleValue::FlexShrink(_) => StyleValue::FlexShrink(style.flex_shrink),
+            StyleValue::Margin(_) => StyleValue::Margin(style.margin),
+            StyleValue::Padding(_) => StyleValue::Padding(style.padding),
+            StyleValue::BorderWidth(_) => StyleValue::BorderWidth(style.border),
+            StyleValue::Inset(_) => StyleValue::Inset(style.inset),
+            StyleValue::Gap(_) => StyleValue::Gap(style.gap),
+        }
+    }
+
+    pub fn apply(self, style: &mut Style) {
+        match self {
+            StyleValue::Width(width) => style.size.width = width,
+            StyleValue::Height(height) => style.size.height = height,
+            StyleValue::MinWidth(width) => style.min_size.width = width,
+            StyleValue::MinHeight(height) => style.min_size.height = height,
+            StyleValue::MaxWidth(width) => style.max_size.width = width,
+            StyleValue::MaxHeight(height) => style.max_size.height = height,
+            StyleValue::FlexBasis(basis) => style.flex_basis = basis,
+            StyleValue::FlexGrow(grow) => style.flex_grow = grow,
+            StyleValue::FlexShrink(shrink) => style.flex_shrink = shrink,
+            StyleValue::Margin(margin) => style.margin = margin,
+            StyleValue::Padding(padding) => style.padding = padding,
+            StyleValue::BorderWidth(border) => style.border = border,
+            StyleValue::Inset(inset) => style.inset = inset,
+            StyleValue::Gap(gap) => style.gap = gap,
+        }
+    }
+
+    fn interpolate(&self, to: &StyleValue, progress: f32) -> StyleValue {
+        match (*self, *to) {
+            (StyleValue::Width(from), StyleValue::Width(to)) => {
+                StyleValue::Width(from.interpolate(&to, progress))
+            }
+            (StyleValue::Height(from), StyleValue::Height(to)) => {
+                StyleValue::Height(from.interpolate(&to, progress))
+            }
+            (StyleValue::MinWidth(from), StyleValue::MinWidth(to)) => {
+                StyleValue::MinWidth(from.interpolate(&to, progress))
+            }
+            (StyleValue::MinHeight(from), StyleValue::MinHeight(to)) => {
+                StyleValue::MinHeight(from.interpolate(&to, progress))
+            }
+            (StyleValue::MaxWidth(from), StyleValue::MaxWidth(to)) => {
+                StyleValue::MaxWidth(from.interpolate(&to, progress))
+            }
+            (StyleValue::MaxHeight(from), StyleValue::MaxHeight(to)) => {
+                StyleValue::MaxHeight(from.interpolate(&to, progress))
+            }
+            (StyleValue::FlexBasis(from), StyleValue::FlexBasis(to)) => {
+                StyleValue::FlexBasis(from.interpolate(&to, progress))
+            }
+            (StyleValue::FlexGrow(from), StyleValue::FlexGrow(to)) => {
+                StyleValue::FlexGrow(from.interpolate(&to, progress).max(0.0))
+            }
+            (StyleValue::FlexShrink(from), StyleValue::FlexShrink(to)) => {
+                StyleValue::FlexShrink(from.interpolate(&to, progress).max(0.0))
+            }
+            (StyleValue::Margin(from), StyleValue::Margin(to)) => {
+                StyleValue::Margin(from.interpolate(&to, progress))
+            }
+            (StyleValue::Padding(from), StyleValue::Padding(to)) => {
+                StyleValue::Padding(from.interpolate(&to, progress))
+            }
+            (StyleValue::BorderWidth(from), StyleValue::BorderWidth(to)) => {
+                StyleValue::BorderWidth(from.interpolate(&to, progress))
+            }
+            (StyleValue::Inset(from), StyleValue::Inset(to)) => {
+                StyleValue::Inset(from.interpolate(&to, progress))
+            }
+            (StyleValue::Gap(from), StyleValue::Gap(to)) => {
+                StyleValue::Gap(from.interpolate(&to, progress))
+            }
+            (_, to) => to,
+        }
+    }
+}
+
+/// The properties the `transition` style can name, `All` stands for every
+/// other one
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub enum TransitionProperty {
+    All,
+    Opacity,
+    Transform,
+    BackgroundColor,
+    BorderColor,
+    BorderRadius,
+    Width,
+    Height,
+    MinWidth,
+    MinHeight,
+    MaxWidth,
+    MaxHeight,
+    FlexBasis,
+    FlexGrow,
+    FlexShrink,
+    Margin,
+    Padding,
+    BorderWidth,
+    Inset,
+    Gap,
+}
+
+impl TransitionProperty {
+    /// Everything `All` covers
+    pub const ANIMATABLE: [TransitionProperty; 19] = [
+        TransitionProperty::Opacity,
+        TransitionProperty::Transform,
+        TransitionProperty::BackgroundColor,
+        TransitionProperty::BorderColor,
+        TransitionProperty::BorderRadius,
+        TransitionProperty::Width,
+        TransitionProperty::Height,
+        TransitionProperty::MinWidth,
+        TransitionProperty::MinHeight,
+        TransitionProperty::MaxWidth,
+        TransitionProperty::MaxHeight,
+        TransitionProperty::FlexBasis,
+        TransitionProperty::FlexGrow,
+        TransitionProperty::FlexShrink,
+        TransitionProperty::Margin,
+        TransitionProperty::Padding,
+        TransitionProperty::BorderWidth,
+        TransitionProperty::Inset,
+        TransitionProperty::Gap,
+    ];
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct Transition {
+    pub property: TransitionProperty,
+    pub timing: Timing,
+}
+
+/// Values that mix like css, kinds that cannot, like lengths and
+/// percentages, switch halfway
+pub trait Interpolate {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        lerp(*self, *to, progress)
+    }
+}
+
+impl Interpolate for LengthPercentage {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        interpolate_length(*self, *to, progress).unwrap_or(discrete(*self, *to, progress))
+    }
+}
+
+impl Interpolate for LengthPercentageAuto {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        match (*self, *to) {
+            (LengthPercentageAuto::Length(from), LengthPercentageAuto::Length(to)) => {
+                LengthPercentageAuto::Length(lerp(from, to, progress))
+            }
+            (LengthPercentageAuto::Percent(from), LengthPercentageAuto::Percent(to)) => {
+                LengthPercentageAuto::Percent(lerp(from, to, progress))
+            }
+            (from, to) => discrete(from, to, progress),
+        }
+    }
+}
+
+impl Interpolate for Dimension {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        match (*self, *to) {
+            (Dimension::Length(from), Dimension::Length(to)) => {
+                Dimension::Length(lerp(from, to, progress))
+            }
+            (Dimension::Percent(from), Dimension::Percent(to)) => {
+                Dimension::Percent(lerp(from, to, progress))
+            }
+            (from, to) => discrete(from, to, progress),
+        }
+    }
+}
+
+impl<T: Interpolate> Interpolate for Size<T> {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        Size {
+            width: self.width.interpolate(&to.width, progress),
+            height: self.height.interpolate(&to.height, progress),
+        }
+    }
+}
+
+impl<T: Interpolate> Interpolate for Rect<T> {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        Rect {
+            left: self.left.interpolate(&to.left, progress),
+            right: self.right.interpolate(&to.right, progress),
+            top: self.top.interpolate(&to.top, progress),
+            bottom: self.bottom.interpolate(&to.bottom, progress),
+        }
+    }
+}
+
+impl Interpolate for BorderRadius {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        BorderRadius {
+            top_left: self.top_left.interpolate(&to.top_left, progress),
+            top_right: self.top_right.interpolate(&to.top_right, progress),
+            bottom_right: self.bottom_right.interpolate(&to.bottom_right, progress),
+            bottom_left: self.bottom_left.interpolate(&to.bottom_left, progress),
+        }
+    }
+}
+
+fn discrete<T>(from: T, to: T, progress: f32) -> T {
+    if progress < 0.5 {
+        from
+    } else {
+        to
+    }
+}
+
+/// Premultiplied, so transparent ends do not fade through black
+fn interpolate_color(from: [f32; 4], to: [f32; 4], progress: f32) -> [f32; 4] {
+    AlphaColor::<Srgb>::new(from)
+        .lerp_rect(AlphaColor::new(to), progress)
+        .components
+        .map(|component| component.clamp(0.0, 1.0))
+}
+
+/// How the progress of an animation follows the time, on `0..1`
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum Easing {
+    Linear,
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    pub fn apply(self, progress: f32) -> f32 {
+        match self {
+            Easing::Linear => progress,
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let t = solve_bezier(x1, x2, progress);
+                bezier(y1, y2, t)
+            }
+        }
+    }
+}
+
+/// Durations and delays are in milliseconds
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum Timing {
+    Tween {
+        duration: f32,
+        delay: f32,
+        easing: Easing,
+    },
+    /// A damped spring pulling the value from the start to the end, its
+    /// velocity is in the distances between them per second
+    Spring {
+        stiffness: f32,
+        damping: f32,
+        mass: f32,
+        velocity: f32,
+        delay: f32,
+    },
+}
+
+/// Springs come to rest once this close to the end and this slow
+const REST_DISTANCE: f32 = 0.001;
+const REST_VELOCITY: f32 = 0.01;
+
+/// Springs that barely lose energy are cut off after this many seconds
+const MAX_SPRING_TIME: f32 = 10.0;
+
+impl Timing {
+    /// Springs need a positive stiffness and mass and a damping of zero or
+    /// more to move at all, anything else only makes NaN
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Timing::Tween { .. } => true,
+            Timing::Spring {
+                stiffness,
+                damping,
+                mass,
+                ..
+            } => stiffness > 0.0 && mass > 0.0 && damping >= 0.0,
+        }
+    }
+
+    /// The progress `elapsed` milliseconds after the start, and whether the
+    /// animation is done
+    fn progress(self, elapsed: f32) -> (f32, bool) {
+        match self {
+            Timing::Tween {
+                duration,
+                delay,
+                easing,
+            } => {
+                let time = elapsed - delay;
+                if time < 0.0 {
+                    (0.0, false)
+                } else if time >= duration {
+                    (1.0, true)
+                } else {
+                    (easing.apply(time / duration), false)
+                }
+            }
+            Timing::Spring {
+                stiffness,
+                damping,
+                mass,
+                velocity,
+                delay,
+            } => {
+                let seconds = (elapsed - delay) / 1000.0;
+                if seconds < 0.0 {
+                    return (0.0, false);
+                }
+
+                let displacement =
+                    |seconds: f32| spring_displacement(stiffness, damping, mass, velocity, seconds);
+                let step = 0.001;
+                let distance = displacement(seconds);
+                let speed = (distance - displacement(seconds + step)) / step;
+
+                let at_rest = distance.abs() < REST_DISTANCE && speed.abs() < REST_VELOCITY;
+                if at_rest || seconds >= MAX_SPRING_TIME {
+                    (1.0, true)
+                } else {
+                    (1.0 + distance, false)
+                }
+            }
+        }
+    }
+}
+
+pub struct Animation {
+    pub id: u32,
+    pub node_id: NodeId,
+    from: AnimatedValue,
+    to: AnimatedValue,
+    timing: Timing,
+    started: Instant,
+}
+
+impl Animation {
+    pub fn new(
+        id: u32,
+        node_id: NodeId,
+        from: AnimatedValue,
+        to: AnimatedValue,
+        timing: Timing,
+    ) -> Self {
+        Self {
+            id,
+            node_id,
+            from,
+            to,
+            timing,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn animates(&self, node_id: NodeId, value: &AnimatedValue) -> bool {
+        self.node_id == node_id && self.to.same_property(value)
+    }
+
+    pub fn target(&self) -> &AnimatedValue {
+        &self.to
+    }
+
+    /// The value at `now` for a node of `size`, and whether the animation is
+    /// done. Done animations end exactly on their target.
+    pub fn sample(&self, now: Instant, size: Size<f32>) -> (AnimatedValue, bool) {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f32() * 1000.0;
+
+        match self.timing.progress(elapsed) {
+            (_, true) => (self.to.clone(), true),
+            (progress, false) => (self.from.interpolate(&self.to, progress, size), false),
+        }
+    }
+}
+
+fn lerp(from: f32, to: f32, progress: f32) -> f32 {
+    from + (to - from) * progress
+}
+
+/// Like css, lists of the same functions mix function by function, with
+/// `none` standing in for identity functions, and anything else mixes the
+/// matrices
+fn interpolate_transforms(
+    from: &[TransformFunction],
+    to: &[TransformFunction],
+    progress: f32,
+    size: Size<f32>,
+) -> Vec<TransformFunction> {
+    let identities = |functions: &[TransformFunction]| -> Vec<TransformFunction> {
+        functions
+            .iter()
+            .map(|function| identity_like(*function))
+            .collect()
+    };
+    let from = if from.is_empty() {
+        identities(to)
+    } else {
+        from.to_vec()
+    };
+    let to = if to.is_empty() {
+        identities(&from)
+    } else {
+        to.to_vec()
+    };
+
+    if from.len() == to.len() {
+        let functions: Option<Vec<TransformFunction>> = from
+            .iter()
+            .zip(&to)
+            .map(|(from, to)| interpolate_function(*from, *to, progress))
+            .collect();
+
+        if let Some(functions) = functions {
+            return functions;
+        }
+    }
+
+    let matrix = |functions: &[TransformFunction]| {
+        functions.iter().fold(Affine::IDENTITY, |matrix, function| {
+            matrix.multiply(function.resolve(size))
+        })
+    };
+    let (from, to) = (matrix(&from).0, matrix(&to).0);
+
+    vec![TransformFunction::Matrix(std::array::from_fn(|index| {
+        lerp(from[index], to[index], progress)
+    }))]
+}
+
+fn interpolate_function(
+    from: TransformFunction,
+    to: TransformFunction,
+    progress: f32,
+) -> Option<TransformFunction> {
+    let function = match (from, to) {
+        (TransformFunction::Translate(x1, y1), TransformFunction::Translate(x2, y2)) => {
+            TransformFunction::Translate(
+                interpolate_length(x1, x2, progress)?,
+                interpolate_length(y1, y2, progress)?,
+            )
+        }
+        (TransformFunction::Scale(x1, y1), TransformFunction::Scale(x2, y2)) => {
+            TransformFunction::Scale(lerp(x1, x2, progress), lerp(y1, y2, progress))
+        }
+        (TransformFunction::Rotate(from), TransformFunction::Rotate(to)) => {
+            TransformFunction::Rotate(lerp(from, to, progress))
+        }
+        (TransformFunction::Skew(x1, y1), TransformFunction::Skew(x2, y2)) => {
+            TransformFunction::Skew(lerp(x1, x2, progress), lerp(y1, y2, progress))
+        }
+        (TransformFunction::Matrix(from), TransformFunction::Matrix(to)) => {
+            TransformFunction::Matrix(std::array::from_fn(|index| {
+                lerp(from[index], to[index], progress)
+            }))
+        }
+        _ => return None,
+    };
+
+    Some(function)
+}
+
+/// Lengths and percentages only mix with their own kind
+fn interpolate_length(
+    from: LengthPercentage,
+    to: LengthPercentage,
+    progress: f32,
+) -> Option<LengthPercentage> {
+    match (from, to) {
+        (LengthPercentage::Length(from), LengthPercentage::Length(to)) => {
+            Some(LengthPercentage::Length(lerp(from, to, progress)))
+        }
+        (LengthPercentage::Percent(from), LengthPercentage::Percent(to)) => {
+            Some(LengthPercentage::Percent(lerp(from, to, progress)))
+        }
+        _ => None,
+    }
+}
+
+/// The function of the same kind, and units, that changes nothing
+fn identity_like(function: TransformFunction) -> TransformFunction {
+    let zero_like = |length: LengthPercentage| match length {
+        LengthPercentage::Length(_) => LengthPercentage::Length(0.0),
+        LengthPercentage::Percent(_) => LengthPercentage::Percent(0.0),
+    };
+
+    match function {
+        TransformFunction::Translate(x, y) => {
+            TransformFunction::Translate(zero_like(x), zero_like(y))
+        }
+        TransformFunction::Scale(_, _) => TransformFunction::Scale(1.0, 1.0),
+        TransformFunction::Rotate(_) => TransformFunction::Rotate(0.0),
+        TransformFunction::Skew(_, _) => TransformFunction::Skew(0.0, 0.0),
+        TransformFunction::Matrix(_) => TransformFunction::Matrix(Affine::IDENTITY.0),
+    }
+}
+
+/// One coordinate of a cubic bezier from (0, 0) to (1, 1) at `t`
+fn bezier(p1: f32, p2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+}
+
+/// The `t` where the curve reaches `x`, by bisection since x only grows
+/// with t for the control points css allows
+fn solve_bezier(x1: f32, x2: f32, x: f32) -> f32 {
+    let (mut low, mut high) = (0.0, 1.0);
+
+    for _ in 0..24 {
+        let middle = (low + high) / 2.0;
+        if bezier(x1, x2, middle) < x {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+/// How far from the end a spring that starts one unit away is after
+/// `seconds`, negative while short of it
+fn spring_displacement(
+    stiffness: f32,
+    damping: f32,
+    mass: f32,
+    velocity: f32,
+    seconds: f32,
+) -> f32 {
+    let start = -1.0;
+    let natural = (stiffness / mass).sqrt();
+    let ratio = damping / (2.0 * (stiffness * mass).sqrt());
+    let decay = (-ratio * natural * seconds).exp();
+
+    if ratio < 1.0 {
+        let damped = natural * (1.0 - ratio * ratio).sqrt();
+        let (sin, cos) = (damped * seconds).sin_cos();
+        decay * (start * cos + (velocity + ratio * natural * start) / damped * sin)
+    } else if ratio == 1.0 {
+        decay * (start + (velocity + natural * start) * seconds)
+    } else {
+        // two decaying exponentials, since cosh and sinh overflow long
+        // before the decay catches up with them
+        let damped = natural * (ratio * ratio - 1.0).sqrt();
+        let (slow, fast) = (-ratio * natural + damped, -ratio * natural - damped);
+        let slow_part = (velocity - fast * start) / (slow - fast);
+        slow_part * (slow * seconds).exp() + (start - slow_part) * (fast * seconds).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spring(stiffness: f32, damping: f32, mass: f32) -> Timing {
+        Timing::Spring {
+            stiffness,
+            damping,
+            mass,
+            velocity: 0.0,
+            delay: 0.0,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn rejects_springs_that_cannot_move() {
+        assert!(spring(100.0, 10.0, 1.0).is_valid());
+        assert!(spring(100.0, 0.0, 1.0).is_valid());
+
+        assert!(!spring(0.0, 10.0, 1.0).is_valid());
+        assert!(!spring(100.0, 10.0, 0.0).is_valid());
+        assert!(!spring(100.0, -1.0, 1.0).is_valid());
+        assert!(!spring(f32::NAN, 10.0, 1.0).is_valid());
+    }
+
+    #[test]
+    fn settles_damped_springs_on_the_end() {
+        for timing in [
+            spring(100.0, 10.0, 1.0),
+            spring(100.0, 20.0, 1.0),
+            spring(100.0, 40.0, 1.0),
+        ] {
+            let (progress, done) = timing.progress(0.0);
+            assert_close(progress, 0.0);
+            assert!(!done);
+
+            assert_eq!(timing.progress(5000.0), (1.0, true));
+        }
+    }
+
+    #[test]
+    fn cuts_off_springs_without_damping() {
+        let timing = spring(100.0, 0.0, 1.0);
+
+        let (progress, done) = timing.progress(MAX_SPRING_TIME * 1000.0 - 10.0);
+        assert!(progress.is_finite());
+        assert!(!done);
+
+        assert_eq!(timing.progress(MAX_SPRING_TIME * 1000.0), (1.0, true));
+    }
+
+    #[test]
+    fn waits_for_the_delay() {
+        let timing = Timing::Tween {
+            duration: 100.0,
+            delay: 50.0,
+            easing: Easing::Linear,
+        };
+
+        assert_eq!(timing.progress(25.0), (0.0, false));
+        assert_eq!(timing.progress(100.0), (0.5, false));
+        assert_eq!(timing.progress(150.0), (1.0, true));
+
+        let timing = Timing::Spring {
+            stiffness: 100.0,
+            damping: 10.0,
+            mass: 1.0,
+            velocity: 0.0,
+            delay: 50.0,
+        };
+        assert_eq!(timing.progress(25.0), (0.0, false));
+    }
+
+    #[test]
+    fn eases_along_cubic_beziers() {
+        let ease = Easing::CubicBezier(0.25, 0.1, 0.25, 1.0);
+
+        assert_close(ease.apply(0.0), 0.0);
+        assert_close(ease.apply(1.0), 1.0);
+        assert!(ease.apply(0.5) > 0.5);
+
+        let linear = Easing::CubicBezier(0.0, 0.0, 1.0, 1.0);
+        for progress in [0.1, 0.3, 0.7] {
+            assert_close(linear.apply(progress), progress);
+        }
+    }
+
+    #[test]
+    fn interpolates_lengths_and_switches_between_kinds_halfway() {
+        let from = StyleValue::Width(Dimension::Length(100.0));
+
+        assert_eq!(
+            from.interpolate(&StyleValue::Width(Dimension::Length(200.0)), 0.25),
+            StyleValue::Width(Dimension::Length(125.0))
+        );
+
+        let to = StyleValue::Width(Dimension::Percent(0.5));
+        assert_eq!(from.interpolate(&to, 0.4), from);
+        assert_eq!(from.interpolate(&to, 0.5), to);
+
+        let margin = |left| Rect {
+            left,
+            right: LengthPercentageAuto::Length(0.0),
+            top: LengthPercentageAuto::Length(10.0),
+            bottom: LengthPercentageAuto::Auto,
+        };
+        let from = StyleValue::Margin(margin(LengthPercentageAuto::Auto));
+        let to = StyleValue::Margin(Rect {
+            top: LengthPercentageAuto::Length(20.0),
+            ..margin(LengthPercentageAuto::Length(8.0))
+        });
+
+        assert_eq!(
+            from.interpolate(&to, 0.25),
+            StyleValue::Margin(Rect {
+                top: LengthPercentageAuto::Length(12.5),
+                ..margin(LengthPercentageAuto::Auto)
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_overshooting_values_in_range() {
+        let size = Size::ZERO;
+
+        assert_eq!(
+            StyleValue::FlexGrow(1.0).interpolate(&StyleValue::FlexGrow(0.0), 1.5),
+            StyleValue::FlexGrow(0.0)
+        );
+        assert_eq!(
+            AnimatedValue::Width(10.0).interpolate(&AnimatedValue::Width(0.0), 1.5, size),
+            AnimatedValue::Width(0.0)
+        );
+
+        let AnimatedValue::BackgroundColor(color) =
+            AnimatedValue::BackgroundColor([0.0, 0.0, 0.0, 1.0]).interpolate(
+                &AnimatedValue::BackgroundColor([1.0; 4]),
+                1.5,
+                size,
+            )
+        else {
+            panic!("expected a color");
+        };
+        assert_eq!(color, [1.0; 4]);
+    }
+
+    #[test]
+    fn interpolates_colors_premultiplied() {
+        let transparent = AnimatedValue::BackgroundColor([0.0; 4]);
+        let red = AnimatedValue::BackgroundColor([1.0, 0.0, 0.0, 1.0]);
+
+        // does not fade through black
+        assert_eq!(
+            transparent.interpolate(&red, 0.5, Size::ZERO),
+            AnimatedValue::BackgroundColor([1.0, 0.0, 0.0, 0.5])
+        );
+    }
+
+    #[test]
+    fn interpolates_border_radii_corner_by_corner() {
+        let radius = |width, height| Size { width, height };
+        let from = BorderRadius {
+            top_left: radius(
+                LengthPercentage::Length(0.0),
+                LengthPercentage::Length(10.0),
+            ),
+            ..BorderRadius::default()
+        };
+        let to = BorderRadius {
+            top_left: radius(
+                LengthPercentage::Length(20.0),
+                LengthPercentage::Length(30.0),
+            ),
+            bottom_right: radius(
+                LengthPercentage::Percent(0.5),
+                LengthPercentage::Percent(0.5),
+            ),
+            ..BorderRadius::default()
+        };
+
+        let middle = from.interpolate(&to, 0.5);
+        assert_eq!(
+            middle.top_left,
+            radius(
+                LengthPercentage::Length(10.0),
+                LengthPercentage::Length(20.0)
+            )
+        );
+        assert_eq!(middle.bottom_right, to.bottom_right);
+        assert_eq!(middle.top_right, from.top_right);
+    }
+
+    #[test]
+    fn interpolates_matching_transforms_function_by_function() {
+        let size = Size {
+            width: 100.0,
+            height: 100.0,
+        };
+        let from = AnimatedValue::Transform(Vec::new());
+        let to = AnimatedValue::Transform(vec![
+            TransformFunction::Translate(
+                LengthPercentage::Percent(1.0),
+                LengthPercentage::Length(40.0),
+            ),
+            TransformFunction::Rotate(1.0),
+        ]);
+
+        assert_eq!(
+            from.interpolate(&to, 0.5, size),
+            AnimatedValue::Transform(vec![
+                TransformFunction::Translate(
+                    LengthPercentage::Percent(0.5),
+                    LengthPercentage::Length(20.0),
+                ),
+                TransformFunction::Rotate(0.5),
+            ])
+        );
+
+        // different functions mix as matrices
+        let from = AnimatedValue::Transform(vec![TransformFunction::Scale(1.0, 1.0)]);
+        let to = AnimatedValue::Transform(vec![TransformFunction::Translate(
+            LengthPercentage::Percent(1.0),
+            LengthPercentage::Length(0.0),
+        )]);
+
+        assert_eq!(
+            from.interpolate(&to, 0.5, size),
+            AnimatedValue::Transform(vec![TransformFunction::Matrix([
+                1.0, 0.0, 0.0, 1.0, 50.0, 0.0
+            ])])
+        );
+    }
+
+    #[test]
+    fn reads_and_applies_styles() {
+        let mut style = Style::default();
+
+        StyleValue::Gap(Size {
+            width: LengthPercentage::Length(4.0),
+            height: LengthPercentage::Percent(0.1),
+        })
+        .apply(&mut style);
+        StyleValue::FlexShrink(2.0).apply(&mut style);
+
+        assert_eq!(
+            StyleValue::Gap(Size::zero()).read(&style),
+            StyleValue::Gap(style.gap)
+        );
+        assert_eq!(style.gap.width, LengthPercentage::Length(4.0));
+        assert_eq!(
+            StyleValue::FlexShrink(0.0).read(&style),
+            StyleValue::FlexShrink(2.0)
+        );
+    }
+
+    #[test]
+    fn treats_pixel_and_style_sizes_as_one_property() {
+        let width = AnimatedValue::Width(10.0);
+        let style_width = AnimatedValue::Style(StyleValue::Width(Dimension::Percent(0.5)));
+        let height = AnimatedValue::Height(10.0);
+
+        assert!(width.same_property(&style_width));
+        assert!(style_width.same_property(&width));
+        assert!(!width.same_property(&height));
+        assert!(
+            !style_width.same_property(&AnimatedValue::Style(StyleValue::Height(Dimension::Auto)))
+        );
+        assert!(AnimatedValue::Style(StyleValue::FlexGrow(1.0))
+            .same_property(&AnimatedValue::Style(StyleValue::FlexGrow(0.0))));
+        assert!(!AnimatedValue::Style(StyleValue::Margin(Rect::zero()))
+            .same_property(&AnimatedValue::Style(StyleValue::Padding(Rect::zero()))));
+        assert!(AnimatedValue::Opacity(1.0).same_property(&AnimatedValue::Opacity(0.0)));
+    }
+}
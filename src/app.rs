@@ -1,91 +1,596 @@
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::sync::mpsc::SendError;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
+use taffy::NodeId;
 use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::ElementState;
+use winit::event::KeyEvent;
+use winit::event::MouseButton;
+use winit::event::MouseScrollDelta;
 use winit::event::WindowEvent;
 use winit::event_loop::ActiveEventLoop;
+use winit::event_loop::EventLoopClosed;
 use winit::event_loop::EventLoopProxy;
+use winit::keyboard::Key;
+use winit::keyboard::ModifiersState;
+use winit::keyboard::NamedKey;
 use winit::window::Window;
 use winit::window::WindowId;
 
+use crate::events::change_focus;
+use crate::events::CloseEvent;
+use crate::events::JsEvent;
+use crate::events::KeyboardEvent;
+use crate::events::PointerEvent;
+use crate::events::ScrollEvent;
 use crate::gpu::Gpu;
 use crate::gui::Gui;
+use crate::javascript_runtime::ScriptEvent;
+use crate::window_options::WindowOptions;
 
 #[derive(Debug)]
 pub enum CustomEvent {
-    GuiUpdate,
+    /// The tree below the root of a window changed
+    GuiUpdate(NodeId),
+    /// An image finished loading, which any window may show
+    ImageLoaded,
+    /// The script waits for the next frame, see `requestAnimationFrame`
+    AnimationFrame,
+    /// The script opened a window for the tree below the root, see
+    /// `Gui::open_window`
+    OpenWindow {
+        root: NodeId,
+        options: WindowOptions,
+    },
+    /// The script changed some options of the window of the root
+    SetWindowOptions {
+        root: NodeId,
+        options: WindowOptions,
+    },
+    /// The window of the root was closed by the script or the user
+    CloseWindow(NodeId),
+    /// The entry script ran, false when it failed to load or threw
+    ScriptLoaded(bool),
+}
+
+/*
+ * Where the gui sends its events: the event loop when there are windows,
+ * and a channel read by `Headless` when there are none
+ */
+#[derive(Clone)]
+pub enum EventSender {
+    EventLoop(EventLoopProxy<CustomEvent>),
+    Channel(Sender<CustomEvent>),
+}
+
+impl EventSender {
+    /// Fails once nothing listens anymore, like while quitting
+    pub fn send_event(&self, event: CustomEvent) -> Result<(), CustomEvent> {
+        match self {
+            EventSender::EventLoop(proxy) => proxy
+                .send_event(event)
+                .map_err(|EventLoopClosed(event)| event),
+            EventSender::Channel(sender) => sender.send(event).map_err(|SendError(event)| event),
+        }
+    }
+}
+
+/// Pixels scrolled per line by wheels that report lines instead of pixels
+const LINE_HEIGHT: f32 = 40.0;
+
+/// A window showing the tree below one of the roots of the gui
+struct AppWindow<'window> {
+    window: Arc<Window>,
+    gpu: Gpu<'window>,
+    /// None for the main window, which shows `Gui::root`
+    root: Option<NodeId>,
+    cursor_position: (f32, f32),
+    pressed_target: Option<NodeId>,
+}
+
+impl AppWindow<'_> {
+    /// The root of the main window changes when the script reloads
+    fn root(&self, gui: &Gui) -> NodeId {
+        self.root.unwrap_or(gui.root)
+    }
 }
 
 pub struct App<'window> {
-    window: Option<Arc<Window>>,
-    gpu: Option<Gpu<'window>>,
+    windows: HashMap<WindowId, AppWindow<'window>>,
+    /// Options of the main window, which opens once the app resumes
+    window_options: WindowOptions,
     pub gui: Arc<Mutex<Gui>>,
+    script_events: Sender<ScriptEvent>,
+    modifiers: ModifiersState,
+    started: Instant,
+    animation_frame_requested: bool,
+    backends: wgpu::Backends,
+    /// Whether the script reloads when it changes, otherwise the app quits
+    /// when it fails to load
+    watch: bool,
+    failed: bool,
 }
 
 impl App<'_> {
-    pub fn new(event_loop: Arc<Mutex<EventLoopProxy<CustomEvent>>>) -> Self {
+    pub fn new(event_loop: Arc<Mutex<EventSender>>, script_events: Sender<ScriptEvent>) -> Self {
         Self {
-            window: None,
-            gpu: None,
+            windows: HashMap::new(),
+            window_options: WindowOptions::default(),
             gui: Arc::new(Mutex::new(Gui::new(event_loop.clone()))),
+            script_events,
+            modifiers: ModifiersState::empty(),
+            started: Instant::now(),
+            animation_frame_requested: false,
+            backends: wgpu::Backends::all(),
+            watch: true,
+            failed: false,
+        }
+    }
+
+    pub fn with_window_options(mut self, window_options: WindowOptions) -> Self {
+        self.window_options = window_options;
+        self
+    }
+
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Whether the app quit because the main window or the script failed
+    pub fn exit_code(&self) -> ExitCode {
+        if self.failed {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+
+    /// Creates a window with its own surface showing the tree below the
+    /// root, the main one for None
+    fn open_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        options: &WindowOptions,
+        root: Option<NodeId>,
+    ) -> Option<WindowId> {
+        let window = match event_loop.create_window(options.to_attributes()) {
+            Ok(window) => Arc::new(window),
+            Err(error) => {
+                eprintln!("Failed to create a window: {error}");
+                return None;
+            }
+        };
+
+        let gpu = match Gpu::new(window.clone(), self.backends) {
+            Ok(gpu) => gpu,
+            Err(error) => {
+                eprintln!("{error}");
+                return None;
+            }
+        };
+
+        options.place(&window);
+
+        if let Ok(mut gui) = self.gui.lock() {
+            let root = root.unwrap_or(gui.root);
+            gui.set_scale_factor(root, window.scale_factor() as f32);
         }
+
+        let id = window.id();
+
+        self.windows.insert(
+            id,
+            AppWindow {
+                window,
+                gpu,
+                root,
+                cursor_position: (0.0, 0.0),
+                pressed_target: None,
+            },
+        );
+
+        Some(id)
+    }
+
+    /// The windows showing the tree below the root
+    fn windows_of(&self, root: NodeId) -> Vec<WindowId> {
+        let Ok(gui) = self.gui.lock() else {
+            return Vec::new();
+        };
+
+        self.windows
+            .iter()
+            .filter(|(_, app_window)| app_window.root(&gui) == root)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Lays out the tree in the logical size of the window and hands what it
+    /// paints to the gpu
+    fn update_scene(&mut self, id: WindowId) {
+        let Some(app_window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        let Ok(mut gui) = self.gui.lock() else {
+            return;
+        };
+
+        let root = app_window.root(&gui);
+        let size = logical_size(&app_window.window);
+        gui.compute_layout(root, size.width, size.height);
+
+        let scene = gui.into_scene(root);
+        app_window.gpu.update_scene(scene, gui.text_system_mut());
+    }
+
+    fn request_redraw(&self, id: WindowId) {
+        if let Some(app_window) = self.windows.get(&id) {
+            app_window.window.request_redraw();
+        }
+    }
+
+    fn dispatch(&self, event: JsEvent) {
+        // the runtime thread is gone if the script failed to load
+        let _ = self.script_events.send(ScriptEvent::Dispatch(event));
+    }
+
+    /// Moves the animations running in the window on to this frame,
+    /// computing the layout only when they changed a size, and tells the
+    /// script which ended
+    fn advance_animations(&mut self, id: WindowId) {
+        let Some(app_window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        let Ok(mut gui) = self.gui.lock() else {
+            return;
+        };
+
+        let root = app_window.root(&gui);
+
+        if gui.is_animating(root) {
+            if gui.advance_animations(root, Instant::now()) {
+                let size = logical_size(&app_window.window);
+                gui.compute_layout(root, size.width, size.height);
+            }
+
+            let scene = gui.into_scene(root);
+            app_window.gpu.update_scene(scene, gui.text_system_mut());
+
+            if gui.is_animating(root) {
+                app_window.window.request_redraw();
+            }
+        }
+
+        for (id, finished) in gui.take_finished_animations() {
+            let _ = self
+                .script_events
+                .send(ScriptEvent::AnimationEnd(id, finished));
+        }
+    }
+
+    fn handle_cursor_moved(&mut self, id: WindowId) {
+        let Some(app_window) = self.windows.get(&id) else {
+            return;
+        };
+        let (x, y) = app_window.cursor_position;
+
+        if let Ok(gui) = self.gui.lock() {
+            if let Some(target) = gui.hit_test(app_window.root(&gui), x, y) {
+                self.dispatch(JsEvent::PointerMove(PointerEvent::new(
+                    &gui, target, x, y, 0,
+                )));
+            }
+        }
+    }
+
+    fn handle_mouse_input(&mut self, id: WindowId, state: ElementState, button: MouseButton) {
+        let Some(app_window) = self.windows.get(&id) else {
+            return;
+        };
+        let (x, y) = app_window.cursor_position;
+        let button = mouse_button_index(button);
+
+        if let Ok(mut gui) = self.gui.lock() {
+            let target = gui.hit_test(app_window.root(&gui), x, y);
+
+            match state {
+                ElementState::Pressed => {
+                    // a press outside every node forgets the last one
+                    if let Some(app_window) = self.windows.get_mut(&id) {
+                        app_window.pressed_target = target;
+                    }
+                    let Some(target) = target else {
+                        return;
+                    };
+
+                    self.dispatch(JsEvent::PointerDown(PointerEvent::new(
+                        &gui, target, x, y, button,
+                    )));
+
+                    let focus_target = gui.focusable_ancestor(target);
+                    for event in change_focus(&mut gui, focus_target) {
+                        self.dispatch(event);
+                    }
+                }
+                ElementState::Released => {
+                    // like the dom, a click only fires for the primary button and
+                    // targets the closest node that got both the press and the
+                    // release. The press is taken on every primary release, the
+                    // ones outside every node included, so it cannot pair with
+                    // a later release.
+                    let pressed_target = if button == 0 {
+                        self.windows
+                            .get_mut(&id)
+                            .and_then(|app_window| app_window.pressed_target.take())
+                    } else {
+                        None
+                    };
+                    let Some(target) = target else {
+                        return;
+                    };
+
+                    self.dispatch(JsEvent::PointerUp(PointerEvent::new(
+                        &gui, target, x, y, button,
+                    )));
+
+                    if let Some(pressed_target) = pressed_target {
+                        if let Some(click_target) = gui.common_ancestor(pressed_target, target) {
+                            self.dispatch(JsEvent::Click(PointerEvent::new(
+                                &gui,
+                                click_target,
+                                x,
+                                y,
+                                button,
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_mouse_wheel(&mut self, id: WindowId, delta: MouseScrollDelta) {
+        let Some(app_window) = self.windows.get(&id) else {
+            return;
+        };
+        let (x, y) = app_window.cursor_position;
+
+        // winit reports how far the content should move, the opposite of
+        // the change of the scroll offset
+        let (delta_x, delta_y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (-x * LINE_HEIGHT, -y * LINE_HEIGHT),
+            MouseScrollDelta::PixelDelta(position) => {
+                let position = position.to_logical::<f32>(app_window.window.scale_factor());
+                (-position.x, -position.y)
+            }
+        };
+
+        if let Ok(mut gui) = self.gui.lock() {
+            let root = app_window.root(&gui);
+
+            if let Some((target, offset)) = gui.scroll_by(root, x, y, delta_x, delta_y) {
+                self.dispatch(JsEvent::Scroll(ScrollEvent::new(target, offset)));
+            }
+        }
+    }
+
+    fn handle_keyboard_input(&mut self, id: WindowId, event: KeyEvent) {
+        let Some(app_window) = self.windows.get(&id) else {
+            return;
+        };
+
+        if let Ok(mut gui) = self.gui.lock() {
+            // the focus may be in another window
+            let root = app_window.root(&gui);
+            let target = gui
+                .focused()
+                .filter(|focused| gui.window_root(*focused) == Some(root))
+                .unwrap_or(root);
+            let keyboard_event = KeyboardEvent::new(&gui, target, &event, self.modifiers);
+
+            match event.state {
+                ElementState::Pressed => self.dispatch(JsEvent::KeyDown(keyboard_event)),
+                ElementState::Released => self.dispatch(JsEvent::KeyUp(keyboard_event)),
+            }
+
+            if event.state == ElementState::Pressed
+                && event.logical_key == Key::Named(NamedKey::Tab)
+            {
+                let next = gui.next_focusable(root, self.modifiers.shift_key());
+                for event in change_focus(&mut gui, next) {
+                    self.dispatch(event);
+                }
+            }
+        }
+    }
+}
+
+/// Size of the window in the logical pixels that layout is in
+fn logical_size(window: &Window) -> LogicalSize<f32> {
+    window.inner_size().to_logical(window.scale_factor())
+}
+
+fn mouse_button_index(button: MouseButton) -> u16 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+        MouseButton::Back => 3,
+        MouseButton::Forward => 4,
+        MouseButton::Other(index) => index,
     }
 }
 
 impl<'window> ApplicationHandler<CustomEvent> for App<'window> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.window.is_none() {
-            let window = Arc::new(
-                event_loop
-                    .create_window(
-                        Window::default_attributes()
-                            .with_position(winit::dpi::PhysicalPosition::new(100, 200))
-                            .with_title("wgpu winit example"),
-                    )
-                    .expect("create window err."),
-            );
-
-            self.window = Some(window.clone());
-            self.gpu = Some(Gpu::new(window.clone()));
+        if self.windows.is_empty() {
+            let options = self.window_options.clone();
+
+            if self.open_window(event_loop, &options, None).is_none() {
+                self.failed = true;
+                event_loop.exit();
+            }
         }
     }
 
-    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: CustomEvent) {
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: CustomEvent) {
         match event {
-            CustomEvent::GuiUpdate => {
-                if let Some(window) = self.window.as_ref() {
-                    if let Some(gpu) = self.gpu.as_mut() {
-                        if let Ok(mut gui) = self.gui.lock() {
-                            let size = window.inner_size();
+            CustomEvent::GuiUpdate(root) => {
+                for id in self.windows_of(root) {
+                    self.update_scene(id);
+                    self.request_redraw(id);
+                }
+            }
+            CustomEvent::ImageLoaded => {
+                let ids: Vec<WindowId> = self.windows.keys().copied().collect();
+
+                for id in ids {
+                    self.update_scene(id);
+                    self.request_redraw(id);
+                }
+            }
+            CustomEvent::AnimationFrame => {
+                self.animation_frame_requested = true;
 
-                            gui.compute_layout(size.width, size.height);
-                            gpu.update_instance_buffer(gui.into_instances());
+                // whichever window draws first runs the callbacks
+                for app_window in self.windows.values() {
+                    app_window.window.request_redraw();
+                }
+            }
+            CustomEvent::OpenWindow { root, options } => {
+                // the script may have closed it again or reloaded meanwhile
+                let is_open = self
+                    .gui
+                    .lock()
+                    .is_ok_and(|gui| gui.window_root(root) == Some(root));
 
-                            window.request_redraw();
-                        }
+                if is_open {
+                    if let Some(id) = self.open_window(event_loop, &options, Some(root)) {
+                        self.update_scene(id);
+                        self.request_redraw(id);
+                    }
+                }
+            }
+            CustomEvent::SetWindowOptions { root, options } => {
+                let ids = self.windows_of(root);
+
+                // the script may start before the main window opens
+                if ids.is_empty() && self.gui.lock().is_ok_and(|gui| gui.root == root) {
+                    self.window_options = std::mem::take(&mut self.window_options).merge(options);
+                    return;
+                }
+
+                for id in ids {
+                    if let Some(app_window) = self.windows.get(&id) {
+                        options.apply(&app_window.window);
                     }
                 }
             }
+            CustomEvent::CloseWindow(root) => {
+                self.windows
+                    .retain(|_, app_window| app_window.root != Some(root));
+            }
+            CustomEvent::ScriptLoaded(loaded) => {
+                // when watching, fixing the script loads it again
+                if !loaded && !self.watch {
+                    self.failed = true;
+                    event_loop.exit();
+                }
+            }
         }
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
-                event_loop.exit();
+                let Some(app_window) = self.windows.get(&id) else {
+                    return;
+                };
+
+                match app_window.root {
+                    // closing the main window quits
+                    None => event_loop.exit(),
+                    // the script hears of it to unmount the window
+                    Some(root) => {
+                        if let Ok(mut gui) = self.gui.lock() {
+                            gui.close_window(root);
+                        }
+                        self.dispatch(JsEvent::Close(CloseEvent::new(root)));
+                    }
+                }
             }
             WindowEvent::Resized(size) => {
-                if let Some(gpu) = self.gpu.as_mut() {
-                    if let Ok(mut gui) = self.gui.lock() {
-                        gui.compute_layout(size.width, size.height);
-                        gpu.update_instance_buffer(gui.into_instances());
+                self.update_scene(id);
 
-                        gpu.set_size(size.width, size.height);
-                    }
+                if let Some(app_window) = self.windows.get_mut(&id) {
+                    app_window.gpu.set_size(size.width, size.height);
                 }
             }
+            // moved to a display with another density, or its setting changed
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                let Some(app_window) = self.windows.get(&id) else {
+                    return;
+                };
+
+                if let Ok(mut gui) = self.gui.lock() {
+                    let root = app_window.root(&gui);
+                    gui.set_scale_factor(root, scale_factor as f32);
+                }
+
+                // the physical size may stay the same, in which case no resize follows
+                self.update_scene(id);
+
+                if let Some(app_window) = self.windows.get_mut(&id) {
+                    let size = app_window.window.inner_size();
+                    app_window.gpu.set_size(size.width, size.height);
+                    app_window.window.request_redraw();
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(app_window) = self.windows.get_mut(&id) {
+                    let position = position.to_logical::<f32>(app_window.window.scale_factor());
+                    app_window.cursor_position = (position.x, position.y);
+                }
+                self.handle_cursor_moved(id);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.handle_mouse_input(id, state, button);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.handle_mouse_wheel(id, delta);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.handle_keyboard_input(id, event);
+            }
             WindowEvent::RedrawRequested => {
-                if let Some(gpu) = self.gpu.as_mut() {
-                    gpu.draw();
+                // the callbacks ask for the frame after this one themselves, so
+                // the windows stop redrawing once none are left
+                if self.animation_frame_requested {
+                    self.animation_frame_requested = false;
+                    let timestamp = self.started.elapsed().as_secs_f64() * 1000.0;
+                    let _ = self
+                        .script_events
+                        .send(ScriptEvent::AnimationFrame(timestamp));
+                }
+
+                self.advance_animations(id);
+
+                if let Some(app_window) = self.windows.get_mut(&id) {
+                    app_window.gpu.draw();
                 }
             }
             _ => (),
@@ -0,0 +1,386 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: react-native-wgpu [OPTIONS] <SCRIPT>
+
+Runs SCRIPT, a module rendering with ReactWGPU, in a window.
+
+Options:
+      --watch            Reload the script when it changes, the default
+                         without --headless
+      --no-watch         Run the script once, quitting when it fails to load
+      --headless         Lay out and render without a window
+  -o, --output <FILE>    Save the rendered frame as png, with --headless
+      --size <WxH>       Size of the window or frame in logical pixels
+      --backend <NAMES>  Graphics backends to use, comma separated:
+                         vulkan, metal, dx12, gl or all (default)
+  -h, --help             Print this help
+  -V, --version          Print the version
+";
+
+/// How to run the app, from the command line
+#[derive(Debug)]
+pub struct Options {
+    /// Absolute path of the entry script
+    pub script: PathBuf,
+    pub watch: bool,
+    pub headless: bool,
+    /// Where headless runs save the frame
+    pub output: Option<PathBuf>,
+    /// Logical size of the main window, or of the frame when headless
+    pub size: Option<[f32; 2]>,
+    pub backends: wgpu::Backends,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Run(Options),
+    Help,
+    Version,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    MissingScript,
+    UnexpectedArgument(String),
+    UnknownOption(String),
+    MissingValue(&'static str),
+    UnexpectedValue(String),
+    InvalidSize(String),
+    UnknownBackend(String),
+    OutputWithoutHeadless,
+    Script(PathBuf, io::Error),
+}
+
+impl CliError {
+    /// Mistakes in the arguments exit with 2 like most command line tools,
+    /// a script that cannot be read with 1
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Script(..) => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::MissingScript => write!(f, "Missing the script to run"),
+            CliError::UnexpectedArgument(argument) => {
+                write!(
+                    f,
+                    "Unexpected argument \"{argument}\", only one script runs at a time"
+                )
+            }
+            CliError::UnknownOption(option) => write!(f, "Unknown option \"{option}\""),
+            CliError::MissingValue(option) => write!(f, "Missing the value of {option}"),
+            CliError::UnexpectedValue(option) => write!(f, "{option} takes no value"),
+            CliError::InvalidSize(size) => {
+                write!(
+                    f,
+                    "Invalid size \"{size}\", expected WIDTHxHEIGHT like 800x600"
+                )
+            }
+            CliError::UnknownBackend(backend) => write!(
+                f,
+                "Unknown backend \"{backend}\", expected vulkan, metal, dx12, gl or all"
+            ),
+            CliError::OutputWithoutHeadless => write!(f, "--output needs --headless"),
+            CliError::Script(path, error) => {
+                write!(f, "Failed to open script {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Parses the arguments after the program name. Relative script paths are
+/// from the working directory.
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, CliError> {
+    let mut args = args.into_iter();
+
+    let mut script = None;
+    // headless runs end once the frame is saved, unless asked to watch
+    let mut watch = None;
+    let mut headless = false;
+    let mut output = None;
+    let mut size = None;
+    let mut backends = wgpu::Backends::all();
+    let mut only_positional = false;
+
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+
+        if only_positional || !text.starts_with('-') {
+            if script.is_some() {
+                return Err(CliError::UnexpectedArgument(text.into_owned()));
+            }
+            script = Some(PathBuf::from(arg));
+            continue;
+        }
+
+        // --size=800x600 as well as --size 800x600
+        let (option, inline_value) = match text.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                (option.to_string(), Some(OsString::from(value)))
+            }
+            _ => (text.into_owned(), None),
+        };
+
+        let takes_value = matches!(option.as_str(), "-o" | "--output" | "--size" | "--backend");
+        if inline_value.is_some() && !takes_value {
+            return Err(CliError::UnexpectedValue(option));
+        }
+
+        let mut value = |name: &'static str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(CliError::MissingValue(name))
+        };
+
+        match option.as_str() {
+            "--" => only_positional = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--watch" => watch = Some(true),
+            "--no-watch" => watch = Some(false),
+            "--headless" => headless = true,
+            "-o" | "--output" => output = Some(PathBuf::from(value("--output")?)),
+            "--size" => size = Some(parse_size(&value("--size")?.to_string_lossy())?),
+            "--backend" => backends = parse_backends(&value("--backend")?.to_string_lossy())?,
+            _ => return Err(CliError::UnknownOption(option)),
+        }
+    }
+
+    if output.is_some() && !headless {
+        return Err(CliError::OutputWithoutHeadless);
+    }
+
+    let script = script.ok_or(CliError::MissingScript)?;
+    let script = script
+        .canonicalize()
+        .map_err(|error| CliError::Script(script, error))?;
+
+    if !script.is_file() {
+        let error = io::Error::new(io::ErrorKind::InvalidInput, "not a file");
+        return Err(CliError::Script(script, error));
+    }
+
+    Ok(Command::Run(Options {
+        script,
+        watch: watch.unwrap_or(!headless),
+        headless,
+        output,
+        size,
+        backends,
+    }))
+}
+
+// "800x600"
+fn parse_size(value: &str) -> Result<[f32; 2], CliError> {
+    let invalid = || CliError::InvalidSize(value.to_string());
+
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: f32 = width.trim().parse().map_err(|_| invalid())?;
+    let height: f32 = height.trim().parse().map_err(|_| invalid())?;
+
+    if !(width >= 1.0 && height >= 1.0 && width.is_finite() && height.is_finite()) {
+        return Err(invalid());
+    }
+
+    Ok([width, height])
+}
+
+// "vulkan", "metal,gl", names as wgpu reads them from WGPU_BACKEND
+fn parse_backends(value: &str) -> Result<wgpu::Backends, CliError> {
+    let mut backends = wgpu::Backends::empty();
+
+    for name in value.split(',') {
+        backends |= match name.trim().to_lowercase().as_str() {
+            "all" => wgpu::Backends::all(),
+            "vulkan" | "vk" => wgpu::Backends::VULKAN,
+            "metal" | "mtl" => wgpu::Backends::METAL,
+            "dx12" | "d3d12" => wgpu::Backends::DX12,
+            "gl" | "gles" | "opengl" => wgpu::Backends::GL,
+            _ => return Err(CliError::UnknownBackend(name.trim().to_string())),
+        };
+    }
+
+    Ok(backends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn run(arguments: &[&str]) -> Options {
+        match parse(args(arguments)) {
+            Ok(Command::Run(options)) => options,
+            other => panic!("expected options for {arguments:?}, got {other:?}"),
+        }
+    }
+
+    fn error(arguments: &[&str]) -> CliError {
+        match parse(args(arguments)) {
+            Err(error) => error,
+            other => panic!("expected an error for {arguments:?}, got {other:?}"),
+        }
+    }
+
+    const SCRIPT: &str = file!();
+
+    #[test]
+    fn parses_defaults() {
+        let options = run(&[SCRIPT]);
+
+        assert_eq!(
+            options.script,
+            PathBuf::from(SCRIPT).canonicalize().unwrap()
+        );
+        assert!(options.watch);
+        assert!(!options.headless);
+        assert_eq!(options.output, None);
+        assert_eq!(options.size, None);
+        assert_eq!(options.backends, wgpu::Backends::all());
+    }
+
+    #[test]
+    fn parses_help_and_version_before_the_script() {
+        assert!(matches!(parse(args(&["--help"])), Ok(Command::Help)));
+        assert!(matches!(parse(args(&["-h", "--bogus"])), Ok(Command::Help)));
+        assert!(matches!(parse(args(&["-V"])), Ok(Command::Version)));
+        assert!(matches!(parse(args(&["--version"])), Ok(Command::Version)));
+    }
+
+    #[test]
+    fn headless_implies_no_watch() {
+        let options = run(&["--headless", SCRIPT]);
+        assert!(options.headless);
+        assert!(!options.watch);
+
+        let options = run(&["--headless", "--watch", SCRIPT]);
+        assert!(options.watch);
+
+        let options = run(&["--watch", "--headless", SCRIPT]);
+        assert!(options.watch);
+
+        let options = run(&["--no-watch", SCRIPT]);
+        assert!(!options.watch);
+    }
+
+    #[test]
+    fn parses_values_inline_and_separate() {
+        let options = run(&["--headless", "-o", "frame.png", "--size=320x240", SCRIPT]);
+        assert_eq!(options.output, Some(PathBuf::from("frame.png")));
+        assert_eq!(options.size, Some([320.0, 240.0]));
+
+        let options = run(&[
+            "--headless",
+            "--output=frame.png",
+            "--size",
+            "320x240",
+            "--backend=gl",
+            SCRIPT,
+        ]);
+        assert_eq!(options.output, Some(PathBuf::from("frame.png")));
+        assert_eq!(options.size, Some([320.0, 240.0]));
+        assert_eq!(options.backends, wgpu::Backends::GL);
+    }
+
+    #[test]
+    fn reads_arguments_after_double_dash_as_the_script() {
+        let options = run(&["--", SCRIPT]);
+        assert_eq!(
+            options.script,
+            PathBuf::from(SCRIPT).canonicalize().unwrap()
+        );
+
+        assert!(matches!(
+            error(&["--", "--help"]),
+            CliError::Script(path, _) if path.as_os_str() == "--help"
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(matches!(error(&[]), CliError::MissingScript));
+        assert!(matches!(error(&["--headless"]), CliError::MissingScript));
+        assert!(matches!(
+            error(&[SCRIPT, SCRIPT]),
+            CliError::UnexpectedArgument(_)
+        ));
+        assert!(matches!(
+            error(&["--bogus", SCRIPT]),
+            CliError::UnknownOption(option) if option == "--bogus"
+        ));
+        assert!(matches!(
+            error(&[SCRIPT, "--size"]),
+            CliError::MissingValue("--size")
+        ));
+        assert!(matches!(
+            error(&["--headless=yes", SCRIPT]),
+            CliError::UnexpectedValue(option) if option == "--headless"
+        ));
+        assert!(matches!(
+            error(&["-o", "frame.png", SCRIPT]),
+            CliError::OutputWithoutHeadless
+        ));
+        assert!(matches!(
+            error(&["does/not/exist.tsx"]),
+            CliError::Script(..)
+        ));
+        assert!(matches!(error(&["src"]), CliError::Script(..)));
+    }
+
+    #[test]
+    fn exits_with_2_for_mistakes_in_the_arguments_and_1_for_the_script() {
+        assert_eq!(error(&[]).exit_code(), 2);
+        assert_eq!(error(&["does/not/exist.tsx"]).exit_code(), 1);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("800x600").unwrap(), [800.0, 600.0]);
+        assert_eq!(parse_size("800X600").unwrap(), [800.0, 600.0]);
+        assert_eq!(parse_size(" 1.5 x 2 ").unwrap(), [1.5, 2.0]);
+
+        for size in [
+            "", "800", "800x", "x600", "0x600", "800x-1", "infx600", "NaNx1",
+        ] {
+            assert!(
+                matches!(parse_size(size), Err(CliError::InvalidSize(_))),
+                "{size}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_backends() {
+        assert_eq!(parse_backends("all").unwrap(), wgpu::Backends::all());
+        assert_eq!(parse_backends("vk").unwrap(), wgpu::Backends::VULKAN);
+        assert_eq!(
+            parse_backends("Metal, gl").unwrap(),
+            wgpu::Backends::METAL | wgpu::Backends::GL
+        );
+        assert_eq!(parse_backends("dx12,d3d12").unwrap(), wgpu::Backends::DX12);
+
+        assert!(matches!(
+            parse_backends("vulkan,webgpu"),
+            Err(CliError::UnknownBackend(name)) if name == "webgpu"
+        ));
+        assert!(matches!(
+            parse_backends(""),
+            Err(CliError::UnknownBackend(_))
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+// Shared by shader.wgsl and text.wgsl, which are appended to this file
+
+// Same layout as `Clip` in gpu.rs
+struct Clip {
+    rect: vec4<f32>,
+    radii_x: vec4<f32>,
+    radii_y: vec4<f32>,
+    // rows of the affine matrix mapping window positions into the clip
+    inverse_transform: array<vec4<f32>, 2>,
+    // size of a window pixel in clip units
+    params: vec4<f32>,
+    // enclosing clip, zero for none and the index plus one otherwise
+    parent: vec4<u32>,
+};
+
+@group(1) @binding(1) var<storage, read> clips: array<Clip>;
+
+// Radius of the corner closest to pos, the radii are in the order
+// top left, top right, bottom right, bottom left
+fn corner_radius(pos: vec2<f32>, size: vec2<f32>, radii_x: vec4<f32>, radii_y: vec4<f32>) -> vec2<f32> {
+    let left = pos.x < size.x * 0.5;
+    var corner: u32;
+    if (pos.y < size.y * 0.5) {
+        corner = select(1u, 0u, left);
+    } else {
+        corner = select(2u, 3u, left);
+    }
+    return vec2<f32>(radii_x[corner], radii_y[corner]);
+}
+
+// Signed distance to a rect at the origin with elliptical corners, negative
+// inside. The corner distance is a first order approximation, which is
+// plenty for a pixel of anti-aliasing.
+fn rounded_rect_sdf(pos: vec2<f32>, size: vec2<f32>, radii_x: vec4<f32>, radii_y: vec4<f32>) -> f32 {
+    let half_size = size * 0.5;
+    let r = corner_radius(pos, size, radii_x, radii_y);
+    let p = abs(pos - half_size);
+    let q = p - half_size + r;
+
+    if (q.x > 0.0 && q.y > 0.0 && r.x > 0.0 && r.y > 0.0) {
+        let n = q / r;
+        let k = length(n);
+        return (k - 1.0) * k / length(n / r);
+    }
+
+    return max(p.x - half_size.x, p.y - half_size.y);
+}
+
+// Coverage of a window position by a clip and all of its enclosing clips,
+// `clip` is zero for none and the index plus one otherwise
+fn clip_coverage(clip: u32, window_pos: vec2<f32>) -> f32 {
+    var coverage = 1.0;
+    var index = clip;
+
+    // enclosing clips always come first, so this ends
+    while (index != 0u) {
+        let shape = clips[index - 1u];
+        let pos = vec3<f32>(window_pos, 1.0);
+        let clip_pos = vec2<f32>(
+            dot(shape.inverse_transform[0].xyz, pos),
+            dot(shape.inverse_transform[1].xyz, pos),
+        );
+        let distance = rounded_rect_sdf(clip_pos - shape.rect.xy, shape.rect.zw, shape.radii_x, shape.radii_y);
+
+        coverage *= clamp(0.5 - distance / shape.params.x, 0.0, 1.0);
+        index = shape.parent.x;
+    }
+
+    return coverage;
+}
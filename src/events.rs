@@ -0,0 +1,187 @@
+use serde::Serialize;
+use taffy::NodeId;
+use taffy::Point;
+use winit::event::KeyEvent;
+use winit::keyboard::Key;
+use winit::keyboard::ModifiersState;
+use winit::keyboard::NamedKey;
+use winit::keyboard::PhysicalKey;
+
+use crate::gui::Gui;
+
+/*
+ * Events sent from the window to the JavaScript runtime, serialized
+ * as `{ type: "pointerdown", ... }` for `__dispatchGuiEvent`
+ */
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JsEvent {
+    PointerDown(PointerEvent),
+    PointerUp(PointerEvent),
+    PointerMove(PointerEvent),
+    Click(PointerEvent),
+    KeyDown(KeyboardEvent),
+    KeyUp(KeyboardEvent),
+    Focus(FocusEvent),
+    Blur(FocusEvent),
+    Scroll(ScrollEvent),
+    Close(CloseEvent),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointerEvent {
+    path: Vec<EventTarget>,
+    window_x: f32,
+    window_y: f32,
+    button: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTarget {
+    id: usize,
+    x: f32,
+    y: f32,
+}
+
+impl PointerEvent {
+    pub fn new(gui: &Gui, target: NodeId, x: f32, y: f32, button: u16) -> Self {
+        let path = gui
+            .event_path(target, x, y)
+            .into_iter()
+            .map(|(id, x, y)| EventTarget {
+                id: usize::from(id),
+                x,
+                y,
+            })
+            .collect();
+
+        Self {
+            path,
+            window_x: x,
+            window_y: y,
+            button,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyboardEvent {
+    path: Vec<usize>,
+    key: String,
+    code: String,
+    repeat: bool,
+    shift_key: bool,
+    ctrl_key: bool,
+    alt_key: bool,
+    meta_key: bool,
+}
+
+impl KeyboardEvent {
+    pub fn new(gui: &Gui, target: NodeId, event: &KeyEvent, modifiers: ModifiersState) -> Self {
+        let path = gui.ancestors(target).into_iter().map(usize::from).collect();
+
+        Self {
+            path,
+            key: key_name(&event.logical_key),
+            code: code_name(&event.physical_key),
+            repeat: event.repeat,
+            shift_key: modifiers.shift_key(),
+            ctrl_key: modifiers.control_key(),
+            alt_key: modifiers.alt_key(),
+            meta_key: modifiers.super_key(),
+        }
+    }
+}
+
+// focus events do not bubble, so their path is only the target
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusEvent {
+    path: Vec<usize>,
+}
+
+impl FocusEvent {
+    pub fn new(target: NodeId) -> Self {
+        Self {
+            path: vec![usize::from(target)],
+        }
+    }
+}
+
+// like in the dom, scroll events do not bubble either
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrollEvent {
+    path: Vec<usize>,
+    scroll_left: f32,
+    scroll_top: f32,
+}
+
+impl ScrollEvent {
+    pub fn new(target: NodeId, offset: Point<f32>) -> Self {
+        Self {
+            path: vec![usize::from(target)],
+            scroll_left: offset.x,
+            scroll_top: offset.y,
+        }
+    }
+}
+
+// the user closed a window opened by the script, only its root hears of it
+#[derive(Debug, Clone, Serialize)]
+pub struct CloseEvent {
+    path: Vec<usize>,
+}
+
+impl CloseEvent {
+    pub fn new(root: NodeId) -> Self {
+        Self {
+            path: vec![usize::from(root)],
+        }
+    }
+}
+
+pub fn change_focus(gui: &mut Gui, next: Option<NodeId>) -> Vec<JsEvent> {
+    let previous = gui.focused();
+
+    if previous == next {
+        return Vec::new();
+    }
+
+    gui.set_focused(next);
+
+    let mut events = Vec::new();
+
+    if let Some(previous) = previous {
+        events.push(JsEvent::Blur(FocusEvent::new(previous)));
+    }
+
+    if let Some(next) = next {
+        events.push(JsEvent::Focus(FocusEvent::new(next)));
+    }
+
+    events
+}
+
+/*
+ * winit names keys and codes like the dom does, except for the space key
+ */
+
+fn key_name(key: &Key) -> String {
+    match key {
+        Key::Named(NamedKey::Space) => " ".to_string(),
+        Key::Named(named) => format!("{named:?}"),
+        Key::Character(character) => character.to_string(),
+        Key::Unidentified(_) => "Unidentified".to_string(),
+        Key::Dead(_) => "Dead".to_string(),
+    }
+}
+
+fn code_name(physical_key: &PhysicalKey) -> String {
+    match physical_key {
+        PhysicalKey::Code(code) => format!("{code:?}"),
+        PhysicalKey::Unidentified(_) => "Unidentified".to_string(),
+    }
+}
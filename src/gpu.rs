@@ -2,20 +2,62 @@ use bytemuck::bytes_of;
 use bytemuck::cast_slice;
 use bytemuck::Pod;
 use bytemuck::Zeroable;
+use cosmic_text::{CacheKey, SwashContent, SwashImage};
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
 use std::sync::Arc;
 use wgpu::util::DeviceExt;
 use wgpu::MemoryHints::Performance;
 use wgpu::ShaderSource;
 use winit::window::Window;
 
+use crate::gradient::{GradientGeometry, ResolvedGradient};
+use crate::gui::BorderStyle;
+use crate::images::{DecodedImage, ImageLoader};
+use crate::text::{Glyph, TextSystem};
+use crate::transform::Affine;
+
+/// Matches `INSTANCE_*` in shader.wgsl
+#[derive(Debug, Copy, Clone)]
+enum InstanceKind {
+    Rect = 0,
+    OuterShadow = 1,
+    InsetShadow = 2,
+}
+
+/*
+ * Read from a storage buffer in shader.wgsl, so every field is 16 byte
+ * aligned and the layout must match `Instance` there
+ */
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Instance {
-    pos: [f32; 2],
-    size: [f32; 2],
+    rect: [f32; 4],
     background_color: [f32; 4],
-    border_radius: f32,
+    border_radii_x: [f32; 4],
+    border_radii_y: [f32; 4],
+    image_rect: [f32; 4],
+    uv_rect: [f32; 4],
+    border_widths: [f32; 4],
+    border_colors: [[f32; 4]; 4],
+    shadow_rect: [f32; 4],
+    shadow_radii_x: [f32; 4],
+    shadow_radii_y: [f32; 4],
+    /// rows of the affine matrix mapping the rect into the window
+    transform: [[f32; 4]; 2],
+    /// outset of the quad beyond `rect`, shadow blur sigma, opacity
+    params: [f32; 4],
+    /// instance kind, border style, clip as in `ClipRegion::clip`,
+    /// gradients as returned by `Scene::push_gradients`
+    flags: [u32; 4],
 }
 
 impl Instance {
@@ -25,34 +67,755 @@ impl Instance {
         width: f32,
         height: f32,
         background_color: [f32; 4],
-        border_radius: f32,
+        border_radii: [[f32; 2]; 4],
     ) -> Self {
         Self {
-            pos: [x, y],
-            size: [width, height],
+            rect: [x, y, width, height],
             background_color,
-            border_radius,
+            border_radii_x: border_radii.map(|[x, _]| x),
+            border_radii_y: border_radii.map(|[_, y]| y),
+            image_rect: [0.0; 4],
+            uv_rect: [0.0; 4],
+            border_widths: [0.0; 4],
+            border_colors: [[0.0; 4]; 4],
+            shadow_rect: [0.0; 4],
+            shadow_radii_x: [0.0; 4],
+            shadow_radii_y: [0.0; 4],
+            transform: Affine::IDENTITY.to_rows(),
+            params: [0.0, 0.0, 1.0, 0.0],
+            flags: [InstanceKind::Rect as u32, BorderStyle::Solid as u32, 0, 0],
         }
     }
+
+    /// Draws an image at `pos` relative to the rect. The texture coordinates
+    /// are filled in once the image is in the atlas.
+    pub fn with_image(mut self, pos: [f32; 2], size: [f32; 2]) -> Self {
+        self.image_rect = [pos[0], pos[1], size[0], size[1]];
+        self
+    }
+
+    /// Widths and colors are in css order: top, right, bottom, left
+    pub fn with_border(
+        mut self,
+        widths: [f32; 4],
+        colors: [[f32; 4]; 4],
+        style: BorderStyle,
+    ) -> Self {
+        self.border_widths = widths;
+        self.border_colors = colors;
+        self.flags[1] = style as u32;
+        self
+    }
+
+    /// Paints gradients over the background color, see
+    /// `Scene::push_gradients`
+    pub fn with_gradients(mut self, gradients: u32) -> Self {
+        self.flags[3] = gradients;
+        self
+    }
+
+    /// Places the rect in the window with `transform` and fades all of it
+    /// by `opacity`
+    pub fn with_transform(mut self, transform: Affine, opacity: f32) -> Self {
+        self.transform = transform.to_rows();
+        self.params[2] = opacity;
+        self
+    }
+
+    /// Turns the instance into a shadow of the rounded rect `shadow_rect`,
+    /// relative to the rect, in the background color. Outer shadows are cut
+    /// out where the rect is, inset shadows are clipped to it.
+    pub fn with_shadow(
+        mut self,
+        shadow_rect: [f32; 4],
+        shadow_radii: [[f32; 2]; 4],
+        blur_sigma: f32,
+        inset: bool,
+    ) -> Self {
+        let [x, y, width, height] = shadow_rect;
+        let [_, _, rect_width, rect_height] = self.rect;
+
+        // three sigmas cover all of the visible blur
+        let outset = if inset {
+            0.0
+        } else {
+            [-x, -y, x + width - rect_width, y + height - rect_height]
+                .into_iter()
+                .fold(0.0f32, f32::max)
+                + blur_sigma * 3.0
+        };
+
+        self.shadow_rect = shadow_rect;
+        self.shadow_radii_x = shadow_radii.map(|[x, _]| x);
+        self.shadow_radii_y = shadow_radii.map(|[_, y]| y);
+        self.params[0] = outset;
+        self.params[1] = blur_sigma;
+        self.flags[0] = if inset {
+            InstanceKind::InsetShadow as u32
+        } else {
+            InstanceKind::OuterShadow as u32
+        };
+        self
+    }
 }
 
-pub struct Gpu<'window> {
-    surface: wgpu::Surface<'window>,
-    config: wgpu::SurfaceConfiguration,
+/// An image drawn by the instance at index `instance`, showing the `crop`
+/// part of it as `[x, y, width, height]` in 0..1
+pub struct ImageDraw {
+    pub instance: usize,
+    pub image: Arc<DecodedImage>,
+    pub crop: [f32; 4],
+}
+
+/// Window space bounds that a run of instances is cut to with a scissor rect
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scissor {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Scissor {
+    pub const EMPTY: Scissor = Scissor {
+        left: 0.0,
+        top: 0.0,
+        right: 0.0,
+        bottom: 0.0,
+    };
+
+    pub fn bounding(points: [(f32, f32); 4]) -> Self {
+        points.into_iter().fold(
+            Scissor {
+                left: f32::INFINITY,
+                top: f32::INFINITY,
+                right: f32::NEG_INFINITY,
+                bottom: f32::NEG_INFINITY,
+            },
+            |bounds, (x, y)| Scissor {
+                left: bounds.left.min(x),
+                top: bounds.top.min(y),
+                right: bounds.right.max(x),
+                bottom: bounds.bottom.max(y),
+            },
+        )
+    }
+
+    pub fn intersect(self, other: Scissor) -> Self {
+        Scissor {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+
+    /// Whole pixels covering the bounds as `[x, y, width, height]`, cut to
+    /// the viewport. None when nothing is left.
+    fn to_pixels(self, viewport: [f32; 2]) -> Option<[u32; 4]> {
+        let left = self.left.floor().max(0.0);
+        let top = self.top.floor().max(0.0);
+        let right = self.right.ceil().min(viewport[0]);
+        let bottom = self.bottom.ceil().min(viewport[1]);
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some([
+            left as u32,
+            top as u32,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ])
+    }
+}
+
+/// Matches `GRADIENT_*` in shader.wgsl
+#[derive(Debug, Copy, Clone)]
+enum GradientKind {
+    Linear = 0,
+    Radial = 1,
+    Conic = 2,
+}
+
+/*
+ * Gradient layer in the background of an instance, read from a storage
+ * buffer like `Instance`, the layout must match `Gradient` in shader.wgsl
+ */
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GradientLayer {
+    /// start and end of a linear gradient, center and radii of a radial
+    /// one, center and start angle of a conic one
+    geometry: [f32; 4],
+    /// gradient kind, first stop, stop count and the layer above plus one,
+    /// zero for none
+    info: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GradientStop {
+    /// premultiplied Oklab
+    color: [f32; 4],
+    /// offset along the gradient
+    offset: [f32; 4],
+}
+
+/// How an instance or glyph is cut off by the overflow of its ancestors
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ClipRegion {
+    /// Axis aligned rectangular clips, applied with a scissor rect
+    pub scissor: Option<Scissor>,
+    /// The innermost of the other clips as an index into the clips plus
+    /// one, zero for none
+    pub clip: u32,
+}
+
+/*
+ * Rounded or transformed clip shape, which the fragment shaders test the
+ * window position against. Read from a storage buffer like `Instance`, the
+ * layout must match `Clip` in clip.wgsl.
+ */
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct Clip {
+    rect: [f32; 4],
+    radii_x: [f32; 4],
+    radii_y: [f32; 4],
+    /// rows of the affine matrix mapping window positions into the clip
+    inverse_transform: [[f32; 4]; 2],
+    /// size of a window pixel in clip units
+    params: [f32; 4],
+    /// enclosing clip as in `ClipRegion::clip`
+    parent: [u32; 4],
+}
+
+impl Clip {
+    pub fn new(
+        rect: [f32; 4],
+        radii: [[f32; 2]; 4],
+        inverse_transform: Affine,
+        parent: u32,
+    ) -> Self {
+        let [a, b, c, d, _, _] = inverse_transform.0;
+        let pixel_size = (a * d - b * c).abs().sqrt().max(1e-4);
+
+        Self {
+            rect,
+            radii_x: radii.map(|[x, _]| x),
+            radii_y: radii.map(|[_, y]| y),
+            inverse_transform: inverse_transform.to_rows(),
+            params: [pixel_size, 0.0, 0.0, 0.0],
+            parent: [parent, 0, 0, 0],
+        }
+    }
+}
+
+/// A run of instances or glyphs drawn with the same scissor rect
+#[derive(Debug, Clone)]
+struct DrawBatch {
+    scissor: Option<Scissor>,
+    range: Range<u32>,
+}
+
+fn push_batch(batches: &mut Vec<DrawBatch>, scissor: Option<Scissor>, index: u32) {
+    match batches.last_mut() {
+        Some(batch) if batch.scissor == scissor && batch.range.end == index => {
+            batch.range.end = index + 1;
+        }
+        _ => batches.push(DrawBatch {
+            scissor,
+            range: index..index + 1,
+        }),
+    }
+}
+
+/// The glyphs of a text, painted over the first `above` instances of the
+/// scene
+struct TextDraw {
+    above: u32,
+    glyphs: Range<usize>,
+}
+
+/// Glyphs painted over the first `above` instances, between them and the
+/// rest
+struct GlyphLayer {
+    above: u32,
+    batches: Vec<DrawBatch>,
+}
+
+/// Instances and texts in draw order, with the images and clips they refer
+/// to
+#[derive(Default)]
+pub struct Scene {
+    instances: Vec<Instance>,
+    images: Vec<ImageDraw>,
+    clips: Vec<Clip>,
+    gradients: Vec<GradientLayer>,
+    gradient_stops: Vec<GradientStop>,
+    batches: Vec<DrawBatch>,
+    glyphs: Vec<Glyph>,
+    texts: Vec<TextDraw>,
+}
+
+impl Scene {
+    /// Returns the index of the instance
+    pub fn push(&mut self, mut instance: Instance, region: ClipRegion) -> usize {
+        let index = self.instances.len();
+
+        instance.flags[2] = region.clip;
+        push_batch(&mut self.batches, region.scissor, index as u32);
+        self.instances.push(instance);
+
+        index
+    }
+
+    pub fn push_image(&mut self, image: ImageDraw) {
+        self.images.push(image);
+    }
+
+    /// Adds the glyphs of a text on top of the instances pushed so far
+    pub fn push_glyphs(&mut self, glyphs: impl IntoIterator<Item = Glyph>) {
+        let start = self.glyphs.len();
+        self.glyphs.extend(glyphs);
+
+        self.texts.push(TextDraw {
+            above: self.instances.len() as u32,
+            glyphs: start..self.glyphs.len(),
+        });
+    }
+
+    /// Adds the gradients of a background, the first one on top like in
+    /// css. Returns the bottom layer plus one, zero for none, from which the
+    /// shader paints them upwards.
+    pub fn push_gradients(&mut self, gradients: impl IntoIterator<Item = ResolvedGradient>) -> u32 {
+        let mut above = 0;
+
+        for ResolvedGradient { geometry, stops } in gradients {
+            if stops.is_empty() {
+                continue;
+            }
+
+            let (kind, geometry) = match geometry {
+                GradientGeometry::Linear { start, end } => {
+                    (GradientKind::Linear, [start[0], start[1], end[0], end[1]])
+                }
+                GradientGeometry::Radial { center, radii } => (
+                    GradientKind::Radial,
+                    [center[0], center[1], radii[0], radii[1]],
+                ),
+                GradientGeometry::Conic { center, from } => {
+                    (GradientKind::Conic, [center[0], center[1], from, 0.0])
+                }
+            };
+
+            let first_stop = self.gradient_stops.len() as u32;
+            self.gradient_stops
+                .extend(stops.iter().map(|(offset, color)| GradientStop {
+                    color: *color,
+                    offset: [*offset, 0.0, 0.0, 0.0],
+                }));
+
+            self.gradients.push(GradientLayer {
+                geometry,
+                info: [kind as u32, first_stop, stops.len() as u32, above],
+            });
+            above = self.gradients.len() as u32;
+        }
+
+        above
+    }
+
+    pub fn clips_mut(&mut self) -> &mut Vec<Clip> {
+        &mut self.clips
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GlyphInstance {
+    pos: [f32; 2],
+    size: [f32; 2],
+    uv_pos: [f32; 2],
+    uv_size: [f32; 2],
+    color: [f32; 4],
+    transform: [[f32; 4]; 2],
+    clip: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct AtlasEntry {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    left: i32,
+    top: i32,
+}
+
+/*
+ * Packs rectangles into a square in rows, left to right and top to bottom
+ */
+struct ShelfPacker {
+    size: u32,
+    cursor_x: u32,
+    cursor_y: u32,
+    row_height: u32,
+}
+
+impl ShelfPacker {
+    fn new(size: u32) -> Self {
+        Self {
+            size,
+            cursor_x: 0,
+            cursor_y: 0,
+            row_height: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.row_height = 0;
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.cursor_x + width > self.size {
+            self.cursor_x = 0;
+            self.cursor_y += self.row_height;
+            self.row_height = 0;
+        }
+
+        if width > self.size || self.cursor_y + height > self.size {
+            return None;
+        }
+
+        let position = (self.cursor_x, self.cursor_y);
+
+        self.cursor_x += width;
+        self.row_height = self.row_height.max(height);
+
+        Some(position)
+    }
+}
+
+struct AtlasTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    size: u32,
+}
+
+impl AtlasTexture {
+    fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        label: &str,
+        size: u32,
+        format: wgpu::TextureFormat,
+        filter: wgpu::FilterMode,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            bind_group,
+            size,
+        }
+    }
+
+    fn write(&self, queue: &wgpu::Queue, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        let bytes_per_pixel = self.texture.format().block_copy_size(None).unwrap_or(1);
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * bytes_per_pixel),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/*
+ * Single channel glyph atlas. When it runs full it gets cleared and the
+ * glyphs of the current frame are rasterized again, glyphs that still do not
+ * fit are left out of the frame.
+ */
+struct GlyphAtlas {
+    atlas: AtlasTexture,
+    packer: ShelfPacker,
+    entries: HashMap<CacheKey, Option<AtlasEntry>>,
+}
+
+impl GlyphAtlas {
+    const SIZE: u32 = 1024;
+
+    fn new(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        Self {
+            atlas: AtlasTexture::new(
+                device,
+                bind_group_layout,
+                "Glyph Atlas",
+                Self::SIZE,
+                wgpu::TextureFormat::R8Unorm,
+                wgpu::FilterMode::Nearest,
+            ),
+            packer: ShelfPacker::new(Self::SIZE),
+            entries: HashMap::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.packer.clear();
+    }
+
+    /// Whether clearing would make room, because some glyphs in the atlas
+    /// are not among `glyphs`
+    fn has_unused(&self, glyphs: &[Glyph]) -> bool {
+        let used: HashSet<CacheKey> = glyphs.iter().map(|glyph| glyph.cache_key).collect();
+        self.entries.keys().any(|key| !used.contains(key))
+    }
+
+    fn allocate(&mut self, queue: &wgpu::Queue, image: &SwashImage) -> Option<AtlasEntry> {
+        let width = image.placement.width;
+        let height = image.placement.height;
+
+        let (x, y) = self.packer.allocate(width, height)?;
+
+        // the atlas only stores coverage, color glyphs are drawn with their alpha
+        let data: Vec<u8> = match image.content {
+            SwashContent::Mask => image.data.clone(),
+            SwashContent::Color | SwashContent::SubpixelMask => {
+                image.data.chunks_exact(4).map(|pixel| pixel[3]).collect()
+            }
+        };
+
+        self.atlas.write(queue, x, y, width, height, &data);
+
+        Some(AtlasEntry {
+            x,
+            y,
+            width,
+            height,
+            left: image.placement.left,
+            top: image.placement.top,
+        })
+    }
+}
+
+/*
+ * RGBA atlas for decoded images, keyed by their path. Works like the glyph
+ * atlas but samples linearly, since images get scaled. It holds at least four
+ * images of the largest size they are decoded in.
+ */
+struct ImageAtlas {
+    atlas: AtlasTexture,
+    packer: ShelfPacker,
+    entries: HashMap<PathBuf, AtlasEntry>,
+}
+
+impl ImageAtlas {
+    const SIZE: u32 = ImageLoader::MAX_TEXTURE_SIZE * 2;
+
+    fn new(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        Self {
+            atlas: AtlasTexture::new(
+                device,
+                bind_group_layout,
+                "Image Atlas",
+                Self::SIZE,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::FilterMode::Linear,
+            ),
+            packer: ShelfPacker::new(Self::SIZE),
+            entries: HashMap::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.packer.clear();
+    }
+
+    /// Whether clearing would make room, because some images in the atlas
+    /// are not among `images`
+    fn has_unused(&self, images: &[ImageDraw]) -> bool {
+        let used: HashSet<&Path> = images
+            .iter()
+            .map(|draw| draw.image.path.as_path())
+            .collect();
+        self.entries
+            .keys()
+            .any(|path| !used.contains(path.as_path()))
+    }
+
+    fn allocate(&mut self, queue: &wgpu::Queue, image: &DecodedImage) -> Option<AtlasEntry> {
+        if let Some(entry) = self.entries.get(&image.path) {
+            return Some(*entry);
+        }
+
+        let width = image.pixels.width();
+        let height = image.pixels.height();
+
+        let (x, y) = self.packer.allocate(width, height)?;
+        self.atlas
+            .write(queue, x, y, width, height, image.pixels.as_raw());
+
+        let entry = AtlasEntry {
+            x,
+            y,
+            width,
+            height,
+            left: 0,
+            top: 0,
+        };
+        self.entries.insert(image.path.clone(), entry);
+
+        Some(entry)
+    }
+}
+
+/*
+ * Pipelines and buffers, independent of where the frame ends up
+ */
+pub struct Renderer {
     device: wgpu::Device,
     queue: wgpu::Queue,
     render_pipeline: wgpu::RenderPipeline,
-    instance_buffer: wgpu::Buffer,
+    instance_layout: wgpu::BindGroupLayout,
+    instance_bind_group: wgpu::BindGroup,
     instance_count: u32,
+    instance_batches: Vec<DrawBatch>,
+    image_atlas: ImageAtlas,
+    text_pipeline: wgpu::RenderPipeline,
+    glyph_atlas: GlyphAtlas,
+    glyph_buffer: wgpu::Buffer,
+    glyph_layers: Vec<GlyphLayer>,
+    glyph_clip_layout: wgpu::BindGroupLayout,
+    glyph_clip_bind_group: wgpu::BindGroup,
     viewport: [f32; 2],
 }
 
+/// Why the gpu could not be set up for a window or offscreen
+#[derive(Debug)]
+pub enum GpuError {
+    Surface(wgpu::CreateSurfaceError),
+    /// None of the backends has an adapter, or one that can draw to the
+    /// window
+    NoAdapter(wgpu::Backends),
+    UnsupportedSurface,
+    Device(wgpu::RequestDeviceError),
+    /// A rendered frame could not be copied back from the gpu
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::Surface(error) => write!(f, "Failed to create a surface: {error}"),
+            GpuError::NoAdapter(backends) => {
+                write!(f, "Failed to find a gpu adapter for backends {backends:?}")
+            }
+            GpuError::UnsupportedSurface => write!(f, "The gpu adapter cannot draw to the window"),
+            GpuError::Device(error) => write!(f, "Failed to create a gpu device: {error}"),
+            GpuError::Readback(error) => write!(f, "Failed to read the frame back: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Gpu(GpuError),
+    Png(png::EncodingError),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Gpu(error) => write!(f, "{error}"),
+            SaveError::Png(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+fn create_instance(backends: wgpu::Backends) -> wgpu::Instance {
+    wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    })
+}
+
+pub struct Gpu<'window> {
+    surface: wgpu::Surface<'window>,
+    config: wgpu::SurfaceConfiguration,
+    renderer: Renderer,
+}
+
 impl<'window> Gpu<'window> {
-    pub fn new(window: Arc<Window>) -> Gpu<'window> {
-        pollster::block_on(Gpu::new_async(window))
+    pub fn new(window: Arc<Window>, backends: wgpu::Backends) -> Result<Gpu<'window>, GpuError> {
+        pollster::block_on(Gpu::new_async(window, backends))
     }
 
-    pub async fn new_async(window: Arc<Window>) -> Gpu<'window> {
+    pub async fn new_async(
+        window: Arc<Window>,
+        backends: wgpu::Backends,
+    ) -> Result<Gpu<'window>, GpuError> {
         /*
          * window
          */
@@ -66,9 +829,10 @@ impl<'window> Gpu<'window> {
          * wgpu
          */
 
-        let instance = wgpu::Instance::default();
-        let surface = instance.create_surface(Arc::clone(&window)).unwrap();
-        let push_const_size = std::mem::size_of::<[f32; 2]>() as u32;
+        let instance = create_instance(backends);
+        let surface = instance
+            .create_surface(Arc::clone(&window))
+            .map_err(GpuError::Surface)?;
 
         /*
          * Jitter when resizing windows on macOS
@@ -101,7 +865,234 @@ impl<'window> Gpu<'window> {
                 compatible_surface: Some(&surface),
             })
             .await
-            .expect("Failed to find an appropriate adapter");
+            .ok_or(GpuError::NoAdapter(backends))?;
+
+        let mut config = surface
+            .get_default_config(&adapter, width, height)
+            .ok_or(GpuError::UnsupportedSurface)?;
+        config.alpha_mode = wgpu::CompositeAlphaMode::PostMultiplied;
+
+        let renderer = Renderer::new_async(&adapter, config.format, viewport).await?;
+
+        surface.configure(&renderer.device, &config);
+
+        Ok(Gpu {
+            surface,
+            config,
+            renderer,
+        })
+    }
+
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.renderer.device, &self.config);
+        self.renderer.viewport = [width as f32, height as f32];
+    }
+
+    pub fn draw(&mut self) {
+        self.renderer.device.poll(wgpu::Maintain::Wait);
+
+        if self.renderer.instance_count == 0 {
+            return;
+        }
+
+        let frame = self
+            .surface
+            .get_current_texture()
+            .expect("Failed to acquire next swap chain texture");
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.renderer.render(&view);
+        frame.present();
+    }
+
+    pub fn update_scene(&mut self, scene: Scene, text_system: &mut TextSystem) {
+        self.renderer.update_scene(scene, text_system);
+    }
+}
+
+/*
+ * Renders into a texture instead of a window, for screenshots and tests
+ */
+pub struct OffscreenGpu {
+    renderer: Renderer,
+    texture: wgpu::Texture,
+    width: u32,
+    height: u32,
+}
+
+impl OffscreenGpu {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(
+        width: u32,
+        height: u32,
+        backends: wgpu::Backends,
+    ) -> Result<OffscreenGpu, GpuError> {
+        pollster::block_on(OffscreenGpu::new_async(width, height, backends))
+    }
+
+    pub async fn new_async(
+        width: u32,
+        height: u32,
+        backends: wgpu::Backends,
+    ) -> Result<OffscreenGpu, GpuError> {
+        let width = width.max(1);
+        let height = height.max(1);
+        let viewport = [width as f32, height as f32];
+
+        let instance = create_instance(backends);
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or(GpuError::NoAdapter(backends))?;
+
+        let renderer = Renderer::new_async(&adapter, Self::FORMAT, viewport).await?;
+        let texture = Self::create_texture(&renderer.device, width, height);
+
+        Ok(OffscreenGpu {
+            renderer,
+            texture,
+            width,
+            height,
+        })
+    }
+
+    fn create_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    pub fn update_scene(&mut self, scene: Scene, text_system: &mut TextSystem) {
+        self.renderer.update_scene(scene, text_system);
+    }
+
+    /// Renders a frame and reads it back as tightly packed RGBA rows
+    pub fn render(&mut self) -> Result<Vec<u8>, GpuError> {
+        let view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.renderer.render(&view);
+
+        // buffer copies need rows aligned to 256 bytes
+        let bytes_per_row = self.width * 4;
+        let padded_bytes_per_row = bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let device = &self.renderer.device;
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.renderer.queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        // the callback has run once the device is idle
+        receiver
+            .recv()
+            .unwrap_or(Err(wgpu::BufferAsyncError))
+            .map_err(GpuError::Readback)?;
+
+        let mut pixels = Vec::with_capacity((bytes_per_row * self.height) as usize);
+
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..bytes_per_row as usize]);
+            }
+        }
+
+        readback_buffer.unmap();
+
+        Ok(pixels)
+    }
+
+    pub fn save_png(&mut self, path: &Path) -> Result<(), SaveError> {
+        let pixels = self.render().map_err(SaveError::Gpu)?;
+        self.encode_png(path, &pixels).map_err(SaveError::Png)
+    }
+
+    fn encode_png(&self, path: &Path, pixels: &[u8]) -> Result<(), png::EncodingError> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(pixels)?;
+
+        Ok(())
+    }
+}
+
+impl Renderer {
+    async fn new_async(
+        adapter: &wgpu::Adapter,
+        format: wgpu::TextureFormat,
+        viewport: [f32; 2],
+    ) -> Result<Renderer, GpuError> {
+        let push_const_size = std::mem::size_of::<[f32; 2]>() as u32;
 
         let (device, queue) = adapter
             .request_device(
@@ -117,12 +1108,7 @@ impl<'window> Gpu<'window> {
                 None,
             )
             .await
-            .expect("Failed to create device");
-
-        let mut config = surface.get_default_config(&adapter, width, height).unwrap();
-        config.alpha_mode = wgpu::CompositeAlphaMode::PostMultiplied;
-
-        surface.configure(&device, &config);
+            .map_err(GpuError::Device)?;
 
         /*
          * push constants
@@ -137,15 +1123,75 @@ impl<'window> Gpu<'window> {
          * instances
          */
 
-        let instances: Vec<Instance> = Vec::new();
+        // clips are at binding 1 in both pipelines, see clip.wgsl
+        let clip_layout_entry = wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
 
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: cast_slice(&instances),
-            usage: wgpu::BufferUsages::VERTEX,
+        let instance_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Instance Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                clip_layout_entry,
+                // gradients and their stops
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    ..clip_layout_entry
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    ..clip_layout_entry
+                },
+            ],
         });
 
-        let instance_count = instances.len() as u32;
+        let instance_bind_group =
+            Self::create_instance_bind_group(&device, &instance_layout, &Scene::default());
+        let instance_count = 0;
+
+        /*
+         * atlases
+         */
+
+        let atlas_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Atlas Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let image_atlas = ImageAtlas::new(&device, &atlas_layout);
 
         /*
          * shader
@@ -153,7 +1199,10 @@ impl<'window> Gpu<'window> {
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+            source: ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("clip.wgsl"),
+                include_str!("shader.wgsl")
+            ))),
         });
 
         /*
@@ -163,8 +1212,8 @@ impl<'window> Gpu<'window> {
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[push_constant_range],
+                bind_group_layouts: &[&atlas_layout, &instance_layout],
+                push_constant_ranges: std::slice::from_ref(&push_constant_range),
             });
 
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -173,8 +1222,73 @@ impl<'window> Gpu<'window> {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: Some("vs_main"),
+                // instances are read from the storage buffer
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                // images may be transparent and are drawn over their parents
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        /*
+         * text
+         */
+
+        let glyph_atlas = GlyphAtlas::new(&device, &atlas_layout);
+
+        let glyph_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Glyph Buffer"),
+            contents: &[],
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let text_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("clip.wgsl"),
+                include_str!("text.wgsl")
+            ))),
+        });
+
+        let glyph_clip_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Glyph Clip Bind Group Layout"),
+            entries: &[clip_layout_entry],
+        });
+
+        let glyph_clip_bind_group =
+            Self::create_glyph_clip_bind_group(&device, &glyph_clip_layout, &[]);
+
+        let text_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&atlas_layout, &glyph_clip_layout],
+            push_constant_ranges: &[push_constant_range],
+        });
+
+        let text_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&text_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &text_shader,
+                entry_point: Some("vs_main"),
                 buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+                    array_stride: std::mem::size_of::<GlyphInstance>() as wgpu::BufferAddress,
                     step_mode: wgpu::VertexStepMode::Instance,
                     attributes: &[
                         wgpu::VertexAttribute {
@@ -190,22 +1304,46 @@ impl<'window> Gpu<'window> {
                         wgpu::VertexAttribute {
                             offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
                             shader_location: 2,
-                            format: wgpu::VertexFormat::Float32x4,
+                            format: wgpu::VertexFormat::Float32x2,
                         },
                         wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                            offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
                             shader_location: 3,
-                            format: wgpu::VertexFormat::Float32,
+                            format: wgpu::VertexFormat::Float32x2,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                            shader_location: 4,
+                            format: wgpu::VertexFormat::Float32x4,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                            shader_location: 5,
+                            format: wgpu::VertexFormat::Float32x4,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                            shader_location: 6,
+                            format: wgpu::VertexFormat::Float32x4,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                            shader_location: 7,
+                            format: wgpu::VertexFormat::Uint32,
                         },
                     ],
                 }],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: &text_shader,
                 entry_point: Some("fs_main"),
                 compilation_options: Default::default(),
-                targets: &[Some(config.format.into())],
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -217,44 +1355,26 @@ impl<'window> Gpu<'window> {
             cache: None,
         });
 
-        Gpu {
-            surface,
-            config,
+        Ok(Renderer {
             device,
             queue,
             render_pipeline,
-            instance_buffer,
+            instance_layout,
+            instance_bind_group,
             instance_count,
+            instance_batches: Vec::new(),
+            image_atlas,
+            text_pipeline,
+            glyph_atlas,
+            glyph_buffer,
+            glyph_layers: Vec::new(),
+            glyph_clip_layout,
+            glyph_clip_bind_group,
             viewport,
-        }
+        })
     }
 
-    pub fn set_size(&mut self, width: u32, height: u32) {
-        let width = width.max(1);
-        let height = height.max(1);
-
-        self.config.width = width;
-        self.config.height = height;
-        self.surface.configure(&self.device, &self.config);
-        self.viewport = [width as f32, height as f32];
-    }
-
-    pub fn draw(&mut self) {
-        self.device.poll(wgpu::Maintain::Wait);
-
-        if self.instance_count == 0 {
-            return;
-        }
-
-        let frame = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
-
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
+    fn render(&mut self, view: &wgpu::TextureView) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -265,7 +1385,7 @@ impl<'window> Gpu<'window> {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
@@ -277,27 +1397,357 @@ impl<'window> Gpu<'window> {
                 occlusion_query_set: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline);
+            // texts go between the instances painted beneath and above them
+            let mut drawn = 0;
 
-            if self.instance_count > 0 {
-                rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytes_of(&self.viewport));
-                rpass.set_vertex_buffer(0, self.instance_buffer.slice(..));
-                rpass.draw(0..6, 0..self.instance_count);
+            for layer in &self.glyph_layers {
+                self.draw_instances(&mut rpass, drawn..layer.above);
+                self.draw_glyphs(&mut rpass, &layer.batches);
+                drawn = layer.above;
             }
+
+            self.draw_instances(&mut rpass, drawn..self.instance_count);
         }
 
         self.queue.submit(Some(encoder.finish()));
-        frame.present();
     }
 
-    pub fn update_instance_buffer(&mut self, instances: Vec<Instance>) {
-        self.instance_buffer = self
+    fn draw_instances(&self, rpass: &mut wgpu::RenderPass, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytes_of(&self.viewport));
+        rpass.set_bind_group(0, &self.image_atlas.atlas.bind_group, &[]);
+        rpass.set_bind_group(1, &self.instance_bind_group, &[]);
+
+        let batches = self.instance_batches.iter().filter_map(|batch| {
+            let start = batch.range.start.max(range.start);
+            let end = batch.range.end.min(range.end);
+
+            (start < end).then_some(DrawBatch {
+                scissor: batch.scissor,
+                range: start..end,
+            })
+        });
+
+        Self::draw_batches(rpass, batches, self.viewport);
+    }
+
+    fn draw_glyphs(&self, rpass: &mut wgpu::RenderPass, batches: &[DrawBatch]) {
+        if batches.is_empty() {
+            return;
+        }
+
+        rpass.set_pipeline(&self.text_pipeline);
+        rpass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytes_of(&self.viewport));
+        rpass.set_bind_group(0, &self.glyph_atlas.atlas.bind_group, &[]);
+        rpass.set_bind_group(1, &self.glyph_clip_bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.glyph_buffer.slice(..));
+
+        Self::draw_batches(rpass, batches.iter().cloned(), self.viewport);
+    }
+
+    fn draw_batches(
+        rpass: &mut wgpu::RenderPass,
+        batches: impl IntoIterator<Item = DrawBatch>,
+        viewport: [f32; 2],
+    ) {
+        let [viewport_width, viewport_height] = viewport;
+
+        for batch in batches {
+            let [x, y, width, height] = match batch.scissor {
+                Some(scissor) => match scissor.to_pixels(viewport) {
+                    Some(pixels) => pixels,
+                    // clipped away entirely
+                    None => continue,
+                },
+                None => [0, 0, viewport_width as u32, viewport_height as u32],
+            };
+
+            rpass.set_scissor_rect(x, y, width, height);
+            rpass.draw(0..6, batch.range);
+        }
+    }
+
+    pub fn update_scene(&mut self, mut scene: Scene, text_system: &mut TextSystem) {
+        let mut image_uvs = self.image_uvs(&scene.images);
+
+        // images of earlier frames make room for the ones of this frame,
+        // those that still do not fit are not drawn
+        if image_uvs.contains(&None) && self.image_atlas.has_unused(&scene.images) {
+            self.image_atlas.clear();
+            image_uvs = self.image_uvs(&scene.images);
+        }
+
+        for (draw, uv_rect) in scene.images.iter().zip(image_uvs) {
+            if let (Some(instance), Some(uv_rect)) =
+                (scene.instances.get_mut(draw.instance), uv_rect)
+            {
+                instance.uv_rect = uv_rect;
+            }
+        }
+
+        self.instance_bind_group =
+            Self::create_instance_bind_group(&self.device, &self.instance_layout, &scene);
+        self.instance_count = scene.instances.len() as u32;
+        self.instance_batches = scene.batches;
+
+        let (mut instances, mut layers, complete) =
+            self.glyph_instances(&scene.glyphs, &scene.texts, text_system);
+
+        // like the images, glyphs that still do not fit are left out
+        if !complete && self.glyph_atlas.has_unused(&scene.glyphs) {
+            self.glyph_atlas.clear();
+            (instances, layers, _) = self.glyph_instances(&scene.glyphs, &scene.texts, text_system);
+        }
+
+        self.glyph_buffer = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
+                label: Some("Glyph Buffer"),
                 contents: cast_slice(&instances),
                 usage: wgpu::BufferUsages::VERTEX,
             });
-        self.instance_count = instances.len() as u32;
+        self.glyph_layers = layers;
+        self.glyph_clip_bind_group =
+            Self::create_glyph_clip_bind_group(&self.device, &self.glyph_clip_layout, &scene.clips);
+    }
+
+    fn create_instance_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        scene: &Scene,
+    ) -> wgpu::BindGroup {
+        let instance_buffer =
+            Self::create_storage_buffer(device, "Instance Buffer", &scene.instances);
+        let clip_buffer = Self::create_storage_buffer(device, "Clip Buffer", &scene.clips);
+        let gradient_buffer =
+            Self::create_storage_buffer(device, "Gradient Buffer", &scene.gradients);
+        let gradient_stop_buffer =
+            Self::create_storage_buffer(device, "Gradient Stop Buffer", &scene.gradient_stops);
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Instance Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: clip_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: gradient_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: gradient_stop_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn create_glyph_clip_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        clips: &[Clip],
+    ) -> wgpu::BindGroup {
+        let clip_buffer = Self::create_storage_buffer(device, "Glyph Clip Buffer", clips);
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Glyph Clip Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 1,
+                resource: clip_buffer.as_entire_binding(),
+            }],
+        })
+    }
+
+    fn create_storage_buffer<T: Pod>(
+        device: &wgpu::Device,
+        label: &str,
+        contents: &[T],
+    ) -> wgpu::Buffer {
+        // bindings cannot be empty, so there is always at least one element
+        let empty = [T::zeroed()];
+        let contents = if contents.is_empty() {
+            &empty[..]
+        } else {
+            contents
+        };
+
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: cast_slice(contents),
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+    }
+
+    /// Texture coordinates of each image in the atlas as `[x, y, width, height]`,
+    /// none for images that do not fit
+    fn image_uvs(&mut self, images: &[ImageDraw]) -> Vec<Option<[f32; 4]>> {
+        let atlas_size = self.image_atlas.atlas.size as f32;
+
+        images
+            .iter()
+            .map(|draw| {
+                let entry = self.image_atlas.allocate(&self.queue, &draw.image)?;
+                let [crop_x, crop_y, crop_width, crop_height] = draw.crop;
+                let width = entry.width as f32;
+                let height = entry.height as f32;
+
+                Some([
+                    (entry.x as f32 + crop_x * width) / atlas_size,
+                    (entry.y as f32 + crop_y * height) / atlas_size,
+                    crop_width * width / atlas_size,
+                    crop_height * height / atlas_size,
+                ])
+            })
+            .collect()
+    }
+
+    /// Glyph instances grouped into the layers they paint in, and whether
+    /// all of them fit into the atlas
+    fn glyph_instances(
+        &mut self,
+        glyphs: &[Glyph],
+        texts: &[TextDraw],
+        text_system: &mut TextSystem,
+    ) -> (Vec<GlyphInstance>, Vec<GlyphLayer>, bool) {
+        let mut instances = Vec::with_capacity(glyphs.len());
+        let mut layers: Vec<GlyphLayer> = Vec::new();
+        let mut complete = true;
+        let atlas_size = self.glyph_atlas.atlas.size as f32;
+
+        for text in texts {
+            if layers.last().is_none_or(|layer| layer.above != text.above) {
+                layers.push(GlyphLayer {
+                    above: text.above,
+                    batches: Vec::new(),
+                });
+            }
+
+            let Some(layer) = layers.last_mut() else {
+                continue;
+            };
+
+            for glyph in &glyphs[text.glyphs.clone()] {
+                let entry = match self.glyph_atlas.entries.get(&glyph.cache_key) {
+                    Some(entry) => *entry,
+                    None => match text_system.rasterize(glyph.cache_key) {
+                        Some(image) if image.placement.width > 0 && image.placement.height > 0 => {
+                            let entry = self.glyph_atlas.allocate(&self.queue, &image);
+                            // a full atlas is not remembered, the glyph may fit after clearing
+                            if entry.is_some() {
+                                self.glyph_atlas.entries.insert(glyph.cache_key, entry);
+                            } else {
+                                complete = false;
+                            }
+                            entry
+                        }
+                        _ => {
+                            self.glyph_atlas.entries.insert(glyph.cache_key, None);
+                            None
+                        }
+                    },
+                };
+
+                if let Some(entry) = entry {
+                    push_batch(
+                        &mut layer.batches,
+                        glyph.clip.scissor,
+                        instances.len() as u32,
+                    );
+                    instances.push(GlyphInstance {
+                        pos: [glyph.x + entry.left as f32, glyph.y - entry.top as f32],
+                        size: [entry.width as f32, entry.height as f32],
+                        uv_pos: [entry.x as f32 / atlas_size, entry.y as f32 / atlas_size],
+                        uv_size: [
+                            entry.width as f32 / atlas_size,
+                            entry.height as f32 / atlas_size,
+                        ],
+                        color: glyph.color,
+                        transform: glyph.transform.to_rows(),
+                        clip: glyph.clip.clip,
+                    });
+                }
+            }
+        }
+
+        (instances, layers, complete)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    // machines without a gpu or a software renderer have no adapter
+    fn offscreen(width: u32, height: u32) -> Option<OffscreenGpu> {
+        match OffscreenGpu::new(width, height, wgpu::Backends::all()) {
+            Ok(gpu) => Some(gpu),
+            Err(GpuError::NoAdapter(_)) => {
+                eprintln!("Skipped, no graphics adapter");
+                None
+            }
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * width + x) * 4) as usize;
+        pixels[start..start + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn reads_back_the_rendered_frame() {
+        // rows of 50 pixels take 200 bytes, which the copy pads to 256
+        let (width, height) = (50, 20);
+        let Some(mut gpu) = offscreen(width, height) else {
+            return;
+        };
+
+        let mut scene = Scene::default();
+        let square = [[0.0; 2]; 4];
+        scene.push(
+            Instance::new(0.0, 0.0, 20.0, 10.0, RED, square),
+            ClipRegion::default(),
+        );
+        scene.push(
+            Instance::new(30.0, 10.0, 20.0, 10.0, BLUE, square),
+            ClipRegion::default(),
+        );
+        gpu.update_scene(scene, &mut TextSystem::new());
+
+        let pixels = gpu.render().unwrap();
+
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        assert_eq!(pixel(&pixels, width, 5, 5), [255, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, width, 5, 15), [0, 0, 0, 0]);
+        assert_eq!(pixel(&pixels, width, 40, 5), [0, 0, 0, 0]);
+        assert_eq!(pixel(&pixels, width, 40, 15), [0, 0, 255, 255]);
+        assert_eq!(pixel(&pixels, width, 49, 19), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn renders_an_empty_scene_transparent() {
+        let Some(mut gpu) = offscreen(64, 4) else {
+            return;
+        };
+
+        gpu.update_scene(Scene::default(), &mut TextSystem::new());
+        let pixels = gpu.render().unwrap();
+
+        assert_eq!(pixels.len(), 64 * 4 * 4);
+        assert!(pixels.iter().all(|&byte| byte == 0));
     }
 }
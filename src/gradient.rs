@@ -0,0 +1,316 @@
+use serde::Deserialize;
+use std::f32::consts::SQRT_2;
+use taffy::prelude::*;
+use taffy::Point;
+
+/// Where the gradient line of a linear gradient points
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum LinearDirection {
+    /// Clockwise from the top in radians, like css
+    Angle(f32),
+    /// `to top right` and the like, -1 or 1 for left or right and top or
+    /// bottom
+    Corner(f32, f32),
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum RadialShape {
+    Circle,
+    Ellipse,
+}
+
+/// How far the ending shape of a radial gradient reaches
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum RadialSize {
+    ClosestSide,
+    FarthestSide,
+    ClosestCorner,
+    FarthestCorner,
+    /// Horizontal and vertical radius, circles only use the first
+    Explicit(LengthPercentage, LengthPercentage),
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum GradientKind {
+    Linear(LinearDirection),
+    Radial {
+        shape: RadialShape,
+        size: RadialSize,
+        position: Point<LengthPercentage>,
+    },
+    /// `from` is in radians clockwise from the top
+    Conic {
+        from: f32,
+        position: Point<LengthPercentage>,
+    },
+}
+
+/// A color in premultiplied Oklab, where the stops are interpolated, and
+/// where along the gradient it is. Stops without a position are spread
+/// evenly between their neighbours.
+#[derive(Debug, Copy, Clone)]
+pub struct ColorStop {
+    pub color: [f32; 4],
+    pub position: Option<LengthPercentage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<ColorStop>,
+}
+
+/// The shape of a gradient once placed in a box
+#[derive(Debug, Copy, Clone)]
+pub enum GradientGeometry {
+    Linear { start: [f32; 2], end: [f32; 2] },
+    Radial { center: [f32; 2], radii: [f32; 2] },
+    Conic { center: [f32; 2], from: f32 },
+}
+
+/// A gradient placed in a box, with its stops as offsets along the gradient
+/// where 0 is the start and 1 the end
+#[derive(Debug, Clone)]
+pub struct ResolvedGradient {
+    pub geometry: GradientGeometry,
+    pub stops: Vec<(f32, [f32; 4])>,
+}
+
+impl Gradient {
+    /// Places the gradient in the box `[x, y, width, height]`
+    pub fn resolve(&self, rect: [f32; 4]) -> ResolvedGradient {
+        let [x, y, width, height] = rect;
+
+        let (geometry, length) = match self.kind {
+            GradientKind::Linear(direction) => {
+                let angle = match direction {
+                    LinearDirection::Angle(angle) => angle,
+                    // the middle of the gradient runs through the other two corners
+                    LinearDirection::Corner(corner_x, corner_y) => {
+                        (corner_x * height).atan2(-corner_y * width)
+                    }
+                };
+
+                let (sin, cos) = angle.sin_cos();
+                let length = (width * sin).abs() + (height * cos).abs();
+                let (center_x, center_y) = (x + width / 2.0, y + height / 2.0);
+                let (half_x, half_y) = (sin * length / 2.0, -cos * length / 2.0);
+
+                let geometry = GradientGeometry::Linear {
+                    start: [center_x - half_x, center_y - half_y],
+                    end: [center_x + half_x, center_y + half_y],
+                };
+                (geometry, length)
+            }
+            GradientKind::Radial {
+                shape,
+                size,
+                position,
+            } => {
+                let center_x = resolve_length(position.x, width);
+                let center_y = resolve_length(position.y, height);
+                let radii = radial_radii(shape, size, [center_x, center_y], [width, height]);
+
+                let geometry = GradientGeometry::Radial {
+                    center: [x + center_x, y + center_y],
+                    radii,
+                };
+                (geometry, radii[0])
+            }
+            GradientKind::Conic { from, position } => {
+                let geometry = GradientGeometry::Conic {
+                    center: [
+                        x + resolve_length(position.x, width),
+                        y + resolve_length(position.y, height),
+                    ],
+                    from,
+                };
+                // lengths make no sense around a circle, only fractions of it
+                (geometry, 0.0)
+            }
+        };
+
+        ResolvedGradient {
+            geometry,
+            stops: resolve_stops(&self.stops, length),
+        }
+    }
+}
+
+/// Radii of the ending shape centered at `center` in a box of `size`, for
+/// ellipses the corner sizes keep the aspect ratio of the side sizes
+fn radial_radii(
+    shape: RadialShape,
+    size: RadialSize,
+    center: [f32; 2],
+    box_size: [f32; 2],
+) -> [f32; 2] {
+    let [center_x, center_y] = center;
+    let [width, height] = box_size;
+    let sides_x = [center_x.abs(), (width - center_x).abs()];
+    let sides_y = [center_y.abs(), (height - center_y).abs()];
+    let (closest_x, farthest_x) = (sides_x[0].min(sides_x[1]), sides_x[0].max(sides_x[1]));
+    let (closest_y, farthest_y) = (sides_y[0].min(sides_y[1]), sides_y[0].max(sides_y[1]));
+
+    match (shape, size) {
+        (RadialShape::Circle, RadialSize::ClosestSide) => [closest_x.min(closest_y); 2],
+        (RadialShape::Circle, RadialSize::FarthestSide) => [farthest_x.max(farthest_y); 2],
+        (RadialShape::Circle, RadialSize::ClosestCorner) => [closest_x.hypot(closest_y); 2],
+        (RadialShape::Circle, RadialSize::FarthestCorner) => [farthest_x.hypot(farthest_y); 2],
+        (RadialShape::Circle, RadialSize::Explicit(radius, _)) => {
+            [resolve_length(radius, width).max(0.0); 2]
+        }
+        (RadialShape::Ellipse, RadialSize::ClosestSide) => [closest_x, closest_y],
+        (RadialShape::Ellipse, RadialSize::FarthestSide) => [farthest_x, farthest_y],
+        (RadialShape::Ellipse, RadialSize::ClosestCorner) => {
+            [closest_x * SQRT_2, closest_y * SQRT_2]
+        }
+        (RadialShape::Ellipse, RadialSize::FarthestCorner) => {
+            [farthest_x * SQRT_2, farthest_y * SQRT_2]
+        }
+        (RadialShape::Ellipse, RadialSize::Explicit(radius_x, radius_y)) => [
+            resolve_length(radius_x, width).max(0.0),
+            resolve_length(radius_y, height).max(0.0),
+        ],
+    }
+}
+
+/// Offsets of the stops along a gradient of `length` pixels, fixed up like
+/// css: the ends default to 0 and 1, no stop comes before the one ahead of
+/// it and those without a position are spaced evenly
+fn resolve_stops(stops: &[ColorStop], length: f32) -> Vec<(f32, [f32; 4])> {
+    let mut offsets: Vec<Option<f32>> = stops
+        .iter()
+        .map(|stop| {
+            stop.position.map(|position| match position {
+                LengthPercentage::Length(pixels) if length > 0.0 => pixels / length,
+                LengthPercentage::Length(_) => 0.0,
+                LengthPercentage::Percent(fraction) => fraction,
+            })
+        })
+        .collect();
+
+    if let Some(first) = offsets.first_mut() {
+        first.get_or_insert(0.0);
+    }
+    if let Some(last) = offsets.last_mut() {
+        last.get_or_insert(1.0);
+    }
+
+    let mut max = f32::NEG_INFINITY;
+    for offset in offsets.iter_mut().flatten() {
+        *offset = offset.max(max);
+        max = *offset;
+    }
+
+    // the first and last offsets are set, so every gap has both ends
+    let mut index = 0;
+    while index < offsets.len() {
+        if offsets[index].is_some() {
+            index += 1;
+            continue;
+        }
+
+        let start = index - 1;
+        let end = (index..offsets.len())
+            .find(|&end| offsets[end].is_some())
+            .unwrap();
+        let (from, to) = (offsets[start].unwrap(), offsets[end].unwrap());
+
+        for (gap, offset) in offsets.iter_mut().enumerate().take(end).skip(index) {
+            let fraction = (gap - start) as f32 / (end - start) as f32;
+            *offset = Some(from + (to - from) * fraction);
+        }
+
+        index = end;
+    }
+
+    offsets
+        .into_iter()
+        .zip(stops)
+        .map(|(offset, stop)| (offset.unwrap_or(0.0), stop.color))
+        .collect()
+}
+
+fn resolve_length(length: LengthPercentage, basis: f32) -> f32 {
+    match length {
+        LengthPercentage::Length(value) => value,
+        LengthPercentage::Percent(fraction) => fraction * basis,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    fn stop(color: [f32; 4], position: Option<LengthPercentage>) -> ColorStop {
+        ColorStop { color, position }
+    }
+
+    fn offsets(stops: &[ColorStop], length: f32) -> Vec<f32> {
+        resolve_stops(stops, length)
+            .into_iter()
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+
+    #[test]
+    fn places_the_ends_at_0_and_1() {
+        let stops = [stop(RED, None), stop(BLUE, None)];
+
+        assert_eq!(resolve_stops(&stops, 100.0), vec![(0.0, RED), (1.0, BLUE)]);
+    }
+
+    #[test]
+    fn spaces_stops_without_a_position_evenly() {
+        let stops = [
+            stop(RED, None),
+            stop(GREEN, None),
+            stop(BLUE, Some(LengthPercentage::Percent(0.6))),
+            stop(RED, None),
+            stop(GREEN, None),
+            stop(BLUE, None),
+        ];
+
+        let expected = [0.0, 0.3, 0.6, 0.733_333_3, 0.866_666_7, 1.0];
+        for (offset, expected) in offsets(&stops, 100.0).into_iter().zip(expected) {
+            assert!((offset - expected).abs() < 1e-6, "{offset} != {expected}");
+        }
+    }
+
+    #[test]
+    fn resolves_lengths_against_the_gradient_length() {
+        let stops = [
+            stop(RED, Some(LengthPercentage::Length(50.0))),
+            stop(BLUE, Some(LengthPercentage::Length(150.0))),
+        ];
+
+        assert_eq!(offsets(&stops, 200.0), vec![0.25, 0.75]);
+        assert_eq!(offsets(&stops, 0.0), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn moves_stops_out_of_order_up_to_the_one_ahead() {
+        let stops = [
+            stop(RED, Some(LengthPercentage::Percent(0.6))),
+            stop(GREEN, Some(LengthPercentage::Percent(0.2))),
+            stop(BLUE, None),
+        ];
+
+        assert_eq!(offsets(&stops, 100.0), vec![0.6, 0.6, 1.0]);
+    }
+
+    #[test]
+    fn resolves_a_single_stop_to_the_start() {
+        assert_eq!(offsets(&[stop(RED, None)], 100.0), vec![0.0]);
+        assert_eq!(
+            offsets(&[stop(RED, Some(LengthPercentage::Percent(0.5)))], 100.0),
+            vec![0.5]
+        );
+        assert!(resolve_stops(&[], 100.0).is_empty());
+    }
+}
@@ -1,28 +1,265 @@
+use crate::animation::{
+    AnimatedValue, Animation, StyleValue, Timing, Transition, TransitionProperty,
+};
 use crate::app::CustomEvent;
-use crate::gpu::Instance;
-use slotmap::{DefaultKey, SlotMap};
+use crate::app::EventSender;
+use crate::gpu::{Clip, ClipRegion, ImageDraw, Instance, Scene, Scissor};
+use crate::gradient::Gradient;
+use crate::images::{ImageLoader, NodeImage, ObjectFit};
+use crate::text::{Text, TextSystem};
+use crate::transform::{Affine, Transform};
+use crate::window_options::WindowOptions;
+use serde::Deserialize;
+use slotmap::{DefaultKey, SecondaryMap, SlotMap};
+use std::collections::HashSet;
 use std::convert::From;
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
 use taffy::{
-    compute_cached_layout, compute_flexbox_layout, compute_grid_layout, compute_root_layout,
-    prelude::*, Cache, Layout, Style,
+    compute_block_layout, compute_cached_layout, compute_flexbox_layout, compute_grid_layout,
+    compute_hidden_layout, compute_leaf_layout, compute_root_layout, prelude::*, Cache, Layout,
+    Overflow, Point, Style,
 };
-use winit::event_loop::EventLoopProxy;
 
 #[derive(Debug, Copy, Clone)]
 enum NodeKind {
+    Block,
     Flexbox,
     Grid,
+    Text,
+    Image,
+}
+
+/// Matches the `BORDER_*` constants in shader.wgsl
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BorderStyle {
+    Solid = 0,
+    Dashed = 1,
+    Dotted = 2,
+    None = 3,
+}
+
+/// Horizontal (`width`) and vertical (`height`) radius of each corner,
+/// percentages are of the node's width and height
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub struct BorderRadius {
+    pub top_left: Size<LengthPercentage>,
+    pub top_right: Size<LengthPercentage>,
+    pub bottom_right: Size<LengthPercentage>,
+    pub bottom_left: Size<LengthPercentage>,
+}
+
+impl Default for BorderRadius {
+    fn default() -> Self {
+        let zero = Size {
+            width: LengthPercentage::Length(0.0),
+            height: LengthPercentage::Length(0.0),
+        };
+
+        Self {
+            top_left: zero,
+            top_right: zero,
+            bottom_right: zero,
+            bottom_left: zero,
+        }
+    }
+}
+
+impl BorderRadius {
+    /// Radii in pixels as `[x, y]` for top left, top right, bottom right and
+    /// bottom left. Like css, all radii shrink by the same factor when
+    /// adjacent ones would overlap.
+    pub fn resolve(&self, size: Size<f32>) -> [[f32; 2]; 4] {
+        fn resolve_length(length: LengthPercentage, basis: f32) -> f32 {
+            match length {
+                LengthPercentage::Length(length) => length.max(0.0),
+                LengthPercentage::Percent(fraction) => (fraction * basis).max(0.0),
+            }
+        }
+
+        let radii = [
+            self.top_left,
+            self.top_right,
+            self.bottom_right,
+            self.bottom_left,
+        ]
+        .map(|radius| {
+            [
+                resolve_length(radius.width, size.width),
+                resolve_length(radius.height, size.height),
+            ]
+        });
+
+        let [top_left, top_right, bottom_right, bottom_left] = radii;
+
+        let scale = [
+            size.width / (top_left[0] + top_right[0]),
+            size.width / (bottom_left[0] + bottom_right[0]),
+            size.height / (top_left[1] + bottom_left[1]),
+            size.height / (top_right[1] + bottom_right[1]),
+        ]
+        .into_iter()
+        .filter(|scale| scale.is_finite())
+        .fold(1.0f32, f32::min);
+
+        radii.map(|[x, y]| [x * scale, y * scale])
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BoxShadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub blur: f32,
+    pub spread: f32,
+    pub color: [f32; 4],
+    pub inset: bool,
+}
+
+impl BoxShadow {
+    /// Outer shadows follow the border box of a node, inset shadows its
+    /// padding box, both in the coordinates of the node
+    pub fn to_instance(
+        self,
+        size: Size<f32>,
+        radii: [[f32; 2]; 4],
+        border: taffy::Rect<f32>,
+    ) -> Instance {
+        // css uses half the blur radius as the standard deviation
+        let blur_sigma = self.blur.max(0.0) * 0.5;
+
+        // radii grow and shrink with the spread, but square corners stay square
+        let spread_radii = |radii: [[f32; 2]; 4], spread: f32| {
+            radii.map(|radius| radius.map(|r| if r > 0.0 { (r + spread).max(0.0) } else { 0.0 }))
+        };
+
+        if self.inset {
+            let width = (size.width - border.left - border.right).max(0.0);
+            let height = (size.height - border.top - border.bottom).max(0.0);
+            let padding_radii = padding_radii(radii, border);
+
+            Instance::new(
+                border.left,
+                border.top,
+                width,
+                height,
+                self.color,
+                padding_radii,
+            )
+            .with_shadow(
+                [
+                    self.offset_x + self.spread,
+                    self.offset_y + self.spread,
+                    (width - self.spread * 2.0).max(0.0),
+                    (height - self.spread * 2.0).max(0.0),
+                ],
+                spread_radii(padding_radii, -self.spread),
+                blur_sigma,
+                true,
+            )
+        } else {
+            Instance::new(0.0, 0.0, size.width, size.height, self.color, radii).with_shadow(
+                [
+                    self.offset_x - self.spread,
+                    self.offset_y - self.spread,
+                    (size.width + self.spread * 2.0).max(0.0),
+                    (size.height + self.spread * 2.0).max(0.0),
+                ],
+                spread_radii(radii, self.spread),
+                blur_sigma,
+                false,
+            )
+        }
+    }
+}
+
+/// The radii of the padding box, which follow the border radii minus the
+/// border widths
+fn padding_radii(radii: [[f32; 2]; 4], border: taffy::Rect<f32>) -> [[f32; 2]; 4] {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+
+    [
+        [top_left[0] - border.left, top_left[1] - border.top],
+        [top_right[0] - border.right, top_right[1] - border.top],
+        [
+            bottom_right[0] - border.right,
+            bottom_right[1] - border.bottom,
+        ],
+        [bottom_left[0] - border.left, bottom_left[1] - border.bottom],
+    ]
+    .map(|radius| radius.map(|r| r.max(0.0)))
+}
+
+/// Whether (x, y) is inside a rect of `size` at the origin with elliptical
+/// corners, using the same corner selection as corner_radius in clip.wgsl
+fn rounded_rect_contains(x: f32, y: f32, size: Size<f32>, radii: [[f32; 2]; 4]) -> bool {
+    let Size { width, height } = size;
+
+    if x < 0.0 || y < 0.0 || x > width || y > height {
+        return false;
+    }
+
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let [radius_x, radius_y] = match (x < width * 0.5, y < height * 0.5) {
+        (true, true) => top_left,
+        (false, true) => top_right,
+        (false, false) => bottom_right,
+        (true, false) => bottom_left,
+    };
+
+    let qx = (x - width * 0.5).abs() - width * 0.5 + radius_x;
+    let qy = (y - height * 0.5).abs() - height * 0.5 + radius_y;
+
+    if qx <= 0.0 || qy <= 0.0 || radius_x <= 0.0 || radius_y <= 0.0 {
+        return true;
+    }
+
+    (qx / radius_x).powi(2) + (qy / radius_y).powi(2) <= 1.0
+}
+
+/// Clips in one direction only reach this far in the other
+const UNCLIPPED: f32 = 1.0e6;
+
+impl BorderStyle {
+    pub fn from_css(value: &str) -> Self {
+        match value {
+            "dashed" => BorderStyle::Dashed,
+            "dotted" => BorderStyle::Dotted,
+            "none" | "hidden" => BorderStyle::None,
+            _ => BorderStyle::Solid,
+        }
+    }
 }
 
 pub struct Node {
     kind: NodeKind,
     style: Style,
+    /// The size the props asked for last, which `style` strays from while
+    /// an animation sizes the node
+    props_size: Size<Dimension>,
     background_color: [f32; 4],
-    border_radius: f32,
+    background_gradients: Vec<Gradient>,
+    border_radius: BorderRadius,
+    border_colors: [[f32; 4]; 4],
+    border_style: BorderStyle,
+    box_shadows: Vec<BoxShadow>,
+    transform: Transform,
+    opacity: f32,
+    z_index: Option<i32>,
+    transitions: Vec<Transition>,
+    scroll_offset: Point<f32>,
+    /// Width of the scrollbars painted over the content while it overflows,
+    /// for scroll containers that reserve no gutter
+    overlay_scrollbar_width: f32,
+    focusable: bool,
+    text: Option<Text>,
+    image: Option<NodeImage>,
     cache: Cache,
     pub layout: Layout,
+    pub parent: Option<NodeId>,
     pub children: Vec<NodeId>,
 }
 
@@ -31,10 +268,25 @@ impl Default for Node {
         Node {
             kind: NodeKind::Flexbox,
             style: Style::default(),
+            props_size: Size::auto(),
             background_color: [0.0, 0.0, 0.0, 0.0],
-            border_radius: 0.0,
+            background_gradients: Vec::new(),
+            border_radius: BorderRadius::default(),
+            border_colors: [[0.0, 0.0, 0.0, 1.0]; 4],
+            border_style: BorderStyle::Solid,
+            box_shadows: Vec::new(),
+            transform: Transform::default(),
+            opacity: 1.0,
+            z_index: None,
+            transitions: Vec::new(),
+            scroll_offset: Point::ZERO,
+            overlay_scrollbar_width: 0.0,
+            focusable: false,
+            text: None,
+            image: None,
             cache: Cache::new(),
             layout: Layout::with_order(0),
+            parent: None,
             children: Vec::new(),
         }
     }
@@ -45,22 +297,473 @@ impl Node {
         self.children.push(node);
         self.cache.clear();
     }
+
+    pub fn insert_child_before(&mut self, node: NodeId, before: NodeId) {
+        let index = self
+            .children
+            .iter()
+            .position(|child_id| *child_id == before)
+            .unwrap_or(self.children.len());
+        self.children.insert(index, node);
+        self.cache.clear();
+    }
+
+    /// Returns whether the node was one of the children
+    pub fn remove_child(&mut self, node: NodeId) -> bool {
+        let count = self.children.len();
+        self.children.retain(|child_id| *child_id != node);
+
+        let removed = self.children.len() != count;
+        if removed {
+            self.cache.clear();
+        }
+        removed
+    }
+
+    /// `display: none` takes the node and its children out of the layout,
+    /// the painting and the focus order
+    pub fn is_hidden(&self) -> bool {
+        self.style.display == Display::None
+    }
+
+    /// Maps the coordinates of the node into those of its parent, placing it
+    /// at its layout location with its transform on top
+    pub fn local_transform(&self) -> Affine {
+        Affine::translate(self.layout.location.x, self.layout.location.y)
+            .multiply(self.transform.resolve(self.layout.size))
+    }
+
+    /// The z-index of the stacking context the node creates, None when it
+    /// paints along with its parent. Like in css, a transform or opacity
+    /// below 1 creates a stacking context too.
+    fn stacking_context(&self) -> Option<i32> {
+        if self.z_index.is_some() || self.opacity < 1.0 || !self.transform.functions.is_empty() {
+            Some(self.z_index.unwrap_or(0))
+        } else {
+            None
+        }
+    }
+
+    /// The current value of the property `like` animates
+    fn animated_value(&self, like: &AnimatedValue) -> AnimatedValue {
+        match like {
+            AnimatedValue::Opacity(_) => AnimatedValue::Opacity(self.opacity),
+            AnimatedValue::Transform(_) => {
+                AnimatedValue::Transform(self.transform.functions.clone())
+            }
+            AnimatedValue::BackgroundColor(_) => {
+                AnimatedValue::BackgroundColor(self.background_color)
+            }
+            AnimatedValue::Width(_) => AnimatedValue::Width(self.layout.size.width),
+            AnimatedValue::Height(_) => AnimatedValue::Height(self.layout.size.height),
+            AnimatedValue::BorderColors(_) => AnimatedValue::BorderColors(self.border_colors),
+            AnimatedValue::BorderRadius(_) => AnimatedValue::BorderRadius(self.border_radius),
+            AnimatedValue::Style(value) => AnimatedValue::Style(value.read(&self.style)),
+        }
+    }
+
+    /// The current value of a property `transition` can name
+    fn transition_value(&self, property: TransitionProperty) -> AnimatedValue {
+        let style = |value: StyleValue| AnimatedValue::Style(value.read(&self.style));
+
+        match property {
+            TransitionProperty::All => unreachable!("`All` is not a property of its own"),
+            TransitionProperty::Opacity => AnimatedValue::Opacity(self.opacity),
+            TransitionProperty::Transform => {
+                AnimatedValue::Transform(self.transform.functions.clone())
+            }
+            TransitionProperty::BackgroundColor => {
+                AnimatedValue::BackgroundColor(self.background_color)
+            }
+            TransitionProperty::BorderColor => AnimatedValue::BorderColors(self.border_colors),
+            TransitionProperty::BorderRadius => AnimatedValue::BorderRadius(self.border_radius),
+            TransitionProperty::Width => style(StyleValue::Width(Dimension::Auto)),
+            TransitionProperty::Height => style(StyleValue::Height(Dimension::Auto)),
+            TransitionProperty::MinWidth => style(StyleValue::MinWidth(Dimension::Auto)),
+            TransitionProperty::MinHeight => style(StyleValue::MinHeight(Dimension::Auto)),
+            TransitionProperty::MaxWidth => style(StyleValue::MaxWidth(Dimension::Auto)),
+            TransitionProperty::MaxHeight => style(StyleValue::MaxHeight(Dimension::Auto)),
+            TransitionProperty::FlexBasis => style(StyleValue::FlexBasis(Dimension::Auto)),
+            TransitionProperty::FlexGrow => style(StyleValue::FlexGrow(0.0)),
+            TransitionProperty::FlexShrink => style(StyleValue::FlexShrink(0.0)),
+            TransitionProperty::Margin => style(StyleValue::Margin(Rect::zero())),
+            TransitionProperty::Padding => style(StyleValue::Padding(Rect::zero())),
+            TransitionProperty::BorderWidth => style(StyleValue::BorderWidth(Rect::zero())),
+            TransitionProperty::Inset => style(StyleValue::Inset(Rect::zero())),
+            TransitionProperty::Gap => style(StyleValue::Gap(Size::zero())),
+        }
+    }
+
+    /// The timing of the last transition naming the property, like css
+    fn transition_timing(&self, property: TransitionProperty) -> Option<Timing> {
+        self.transitions
+            .iter()
+            .rev()
+            .find(|transition| {
+                transition.property == property || transition.property == TransitionProperty::All
+            })
+            .map(|transition| transition.timing)
+    }
+
+    fn set_animated_value(&mut self, value: AnimatedValue) {
+        match value {
+            AnimatedValue::Opacity(opacity) => self.opacity = opacity.clamp(0.0, 1.0),
+            AnimatedValue::Transform(functions) => self.transform.functions = functions,
+            AnimatedValue::BackgroundColor(color) => self.background_color = color,
+            AnimatedValue::Width(width) => self.style.size.width = length(width),
+            AnimatedValue::Height(height) => self.style.size.height = length(height),
+            AnimatedValue::BorderColors(colors) => self.border_colors = colors,
+            AnimatedValue::BorderRadius(radius) => self.border_radius = radius,
+            AnimatedValue::Style(value) => value.apply(&mut self.style),
+        }
+    }
+
+    /// Moves the children of the node by its scroll offset
+    pub fn scroll_transform(&self) -> Affine {
+        Affine::translate(-self.scroll_offset.x, -self.scroll_offset.y)
+    }
+
+    /// How far the children can be scrolled on each axis, zero on axes
+    /// that do not scroll
+    pub fn max_scroll(&self) -> Point<f32> {
+        let scrolls = |overflow: Overflow| overflow == Overflow::Scroll;
+
+        Point {
+            x: if scrolls(self.style.overflow.x) {
+                self.layout.scroll_width()
+            } else {
+                0.0
+            },
+            y: if scrolls(self.style.overflow.y) {
+                self.layout.scroll_height()
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Clamps the offset to what the content allows, returns whether the
+    /// scroll offset changed
+    fn set_scroll_offset(&mut self, offset: Point<f32>) -> bool {
+        let max = self.max_scroll();
+        let offset = Point {
+            x: offset.x.clamp(0.0, max.x),
+            y: offset.y.clamp(0.0, max.y),
+        };
+
+        let changed = offset != self.scroll_offset;
+        self.scroll_offset = offset;
+        changed
+    }
+
+    /// Thumbs of the scrollbars, placed in the gutters taffy reserves for
+    /// them along the right and bottom edges, or over the content there when
+    /// it overflows a node without gutters
+    fn scrollbar_thumbs(&self) -> Vec<Instance> {
+        let Layout { size, border, .. } = self.layout;
+        let max = self.max_scroll();
+        let thickness = |gutter: f32, overflows: bool| {
+            if gutter == 0.0 && overflows {
+                self.overlay_scrollbar_width
+            } else {
+                gutter
+            }
+        };
+        let scrollbar_size = Size {
+            width: thickness(self.layout.scrollbar_size.width, max.y > 0.0),
+            height: thickness(self.layout.scrollbar_size.height, max.x > 0.0),
+        };
+        let mut thumbs = Vec::new();
+
+        if scrollbar_size.width > 0.0 && max.y > 0.0 {
+            let track = size.height - border.top - border.bottom - scrollbar_size.height;
+            let (start, length) = thumb_extent(track, max.y, self.scroll_offset.y);
+
+            thumbs.push(scrollbar_thumb(
+                size.width - border.right - scrollbar_size.width,
+                border.top + start,
+                scrollbar_size.width,
+                length,
+            ));
+        }
+
+        if scrollbar_size.height > 0.0 && max.x > 0.0 {
+            let track = size.width - border.left - border.right - scrollbar_size.width;
+            let (start, length) = thumb_extent(track, max.x, self.scroll_offset.x);
+
+            thumbs.push(scrollbar_thumb(
+                border.left + start,
+                size.height - border.bottom - scrollbar_size.height,
+                length,
+                scrollbar_size.height,
+            ));
+        }
+
+        thumbs
+    }
+
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        let radii = self.border_radius.resolve(self.layout.size);
+        rounded_rect_contains(x, y, self.layout.size, radii)
+    }
+
+    /// The padding box that `overflow` cuts the children off at, with
+    /// infinite extent in directions that are not clipped. None when
+    /// nothing is clipped.
+    fn overflow_clip(&self) -> Option<([f32; 4], [[f32; 2]; 4])> {
+        let clips = |overflow: Overflow| overflow != Overflow::Visible;
+        let (clip_x, clip_y) = (clips(self.style.overflow.x), clips(self.style.overflow.y));
+
+        if !clip_x && !clip_y {
+            return None;
+        }
+
+        let Layout { size, border, .. } = self.layout;
+
+        let (x, width) = if clip_x {
+            (
+                border.left,
+                (size.width - border.left - border.right).max(0.0),
+            )
+        } else {
+            (-UNCLIPPED, UNCLIPPED * 2.0)
+        };
+        let (y, height) = if clip_y {
+            (
+                border.top,
+                (size.height - border.top - border.bottom).max(0.0),
+            )
+        } else {
+            (-UNCLIPPED, UNCLIPPED * 2.0)
+        };
+
+        // corners are only rounded when clipping in both directions
+        let radii = if clip_x && clip_y {
+            padding_radii(self.border_radius.resolve(size), border)
+        } else {
+            [[0.0; 2]; 4]
+        };
+
+        Some(([x, y, width, height], radii))
+    }
+
+    /// Whether children at (x, y) are visible through the overflow clip
+    pub fn clip_contains_point(&self, x: f32, y: f32) -> bool {
+        match self.overflow_clip() {
+            Some(([clip_x, clip_y, width, height], radii)) => {
+                rounded_rect_contains(x - clip_x, y - clip_y, Size { width, height }, radii)
+            }
+            None => true,
+        }
+    }
+
+    /// The region the children of the node are drawn in, given the region
+    /// of the node and its transform into the window. Rectangular clips
+    /// that stay axis aligned become scissor rects, all others are added to
+    /// `clips`.
+    pub fn clip_children(
+        &self,
+        transform: Affine,
+        region: ClipRegion,
+        clips: &mut Vec<Clip>,
+    ) -> ClipRegion {
+        let Some((rect, radii)) = self.overflow_clip() else {
+            return region;
+        };
+
+        let Some(inverse_transform) = transform.invert() else {
+            // nothing of a node scaled down to nothing shows
+            return ClipRegion {
+                scissor: Some(Scissor::EMPTY),
+                ..region
+            };
+        };
+
+        let [x, y, width, height] = rect;
+        let bounds = Scissor::bounding(
+            [
+                (x, y),
+                (x + width, y),
+                (x + width, y + height),
+                (x, y + height),
+            ]
+            .map(|(x, y)| transform.apply(x, y)),
+        );
+        let scissor = Some(
+            region
+                .scissor
+                .map_or(bounds, |scissor| scissor.intersect(bounds)),
+        );
+
+        let [_, b, c, _, _, _] = transform.0;
+        let axis_aligned = b == 0.0 && c == 0.0;
+        let rounded = radii.iter().flatten().any(|radius| *radius > 0.0);
+
+        if axis_aligned && !rounded {
+            return ClipRegion {
+                scissor,
+                clip: region.clip,
+            };
+        }
+
+        clips.push(Clip::new(rect, radii, inverse_transform, region.clip));
+
+        ClipRegion {
+            scissor,
+            clip: clips.len() as u32,
+        }
+    }
+}
+
+/// A step in painting the tree, the scrollbars of a node go on top of its
+/// children
+#[derive(Debug, Copy, Clone)]
+enum Paint {
+    Node(NodeId),
+    Scrollbars(NodeId),
+}
+
+/// A root of the tree that is shown in a window of its own
+#[derive(Debug, Copy, Clone)]
+struct WindowRoot {
+    /// Physical pixels of the window per logical pixel, which layout is in
+    scale_factor: f32,
+}
+
+impl Default for WindowRoot {
+    fn default() -> Self {
+        Self { scale_factor: 1.0 }
+    }
+}
+
+/// Orders the painting of the tree like css stacking contexts do: the
+/// node that creates the context, the contexts inside it with a negative
+/// z-index, the rest of its content in tree order, and then the contexts
+/// with a positive z-index. Contexts with equal z-index stay in tree
+/// order.
+fn paint_order(nodes: &SlotMap<DefaultKey, Node>, root: NodeId) -> Vec<Paint> {
+    fn paint_content(
+        nodes: &SlotMap<DefaultKey, Node>,
+        node_id: NodeId,
+        paints: &mut Vec<Paint>,
+        layers: &mut Vec<(i32, NodeId)>,
+    ) {
+        let node = &nodes[node_id.into()];
+
+        if node.is_hidden() {
+            return;
+        }
+
+        match node.stacking_context() {
+            Some(0) => paint_stacking_context(nodes, node_id, paints),
+            Some(z_index) => layers.push((z_index, node_id)),
+            None => {
+                paints.push(Paint::Node(node_id));
+                for child_id in &node.children {
+                    paint_content(nodes, *child_id, paints, layers);
+                }
+                paints.push(Paint::Scrollbars(node_id));
+            }
+        }
+    }
+
+    fn paint_stacking_context(
+        nodes: &SlotMap<DefaultKey, Node>,
+        node_id: NodeId,
+        paints: &mut Vec<Paint>,
+    ) {
+        let mut content = Vec::new();
+        let mut layers = Vec::new();
+
+        for child_id in &nodes[node_id.into()].children {
+            paint_content(nodes, *child_id, &mut content, &mut layers);
+        }
+
+        // stable, so equal z-indices keep their tree order
+        layers.sort_by_key(|(z_index, _)| *z_index);
+        let split = layers.partition_point(|(z_index, _)| *z_index < 0);
+
+        paints.push(Paint::Node(node_id));
+        for (_, layer_id) in &layers[..split] {
+            paint_stacking_context(nodes, *layer_id, paints);
+        }
+        paints.extend(content);
+        paints.push(Paint::Scrollbars(node_id));
+        for (_, layer_id) in &layers[split..] {
+            paint_stacking_context(nodes, *layer_id, paints);
+        }
+    }
+
+    let mut paints = Vec::new();
+    paint_stacking_context(nodes, root, &mut paints);
+    paints
+}
+
+const SCROLLBAR_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.6];
+const SCROLLBAR_INSET: f32 = 2.0;
+const MIN_THUMB_LENGTH: f32 = 20.0;
+
+/// Start and length of a scrollbar thumb on a track showing `track` of the
+/// content, when the content scrolls another `max` past it
+fn thumb_extent(track: f32, max: f32, offset: f32) -> (f32, f32) {
+    let length = (track * track / (track + max))
+        .max(MIN_THUMB_LENGTH)
+        .min(track);
+
+    ((track - length) * offset / max, length)
+}
+
+/// A thumb with fully rounded ends, inset a little from its gutter
+fn scrollbar_thumb(x: f32, y: f32, width: f32, height: f32) -> Instance {
+    let width = (width - SCROLLBAR_INSET * 2.0).max(0.0);
+    let height = (height - SCROLLBAR_INSET * 2.0).max(0.0);
+    let radius = width.min(height) / 2.0;
+
+    Instance::new(
+        x + SCROLLBAR_INSET,
+        y + SCROLLBAR_INSET,
+        width,
+        height,
+        SCROLLBAR_COLOR,
+        [[radius; 2]; 4],
+    )
 }
 
 pub struct Gui {
+    /// Root of the main window, the container the script renders into
     pub root: NodeId,
     nodes: SlotMap<DefaultKey, Node>,
-    event_loop: Arc<Mutex<EventLoopProxy<CustomEvent>>>,
+    focused: Option<NodeId>,
+    text_system: TextSystem,
+    images: ImageLoader,
+    animations: Vec<Animation>,
+    next_animation_id: u32,
+    /// Ids of the animations that ended since the last frame, and whether
+    /// they ran to the end instead of being cancelled
+    finished_animations: Vec<(u32, bool)>,
+    /// The main root and those of the windows opened by the script
+    windows: SecondaryMap<DefaultKey, WindowRoot>,
+    event_loop: Arc<Mutex<EventSender>>,
 }
 
 #[allow(dead_code)]
 impl Gui {
-    pub fn new(event_loop: Arc<Mutex<EventLoopProxy<CustomEvent>>>) -> Self {
+    pub fn new(event_loop: Arc<Mutex<EventSender>>) -> Self {
         let mut nodes = SlotMap::new();
-        let root = nodes.insert(Self::create_root()).into();
+        let root = nodes.insert(Self::create_root());
+        let mut windows = SecondaryMap::new();
+        windows.insert(root, WindowRoot::default());
+
         Self {
-            root,
+            root: root.into(),
             nodes,
+            focused: None,
+            text_system: TextSystem::new(),
+            images: ImageLoader::new(event_loop.clone()),
+            animations: Vec::new(),
+            next_animation_id: 0,
+            finished_animations: Vec::new(),
+            windows,
             event_loop,
         }
     }
@@ -86,37 +789,575 @@ impl Gui {
         &mut self,
         style: Style,
         background_color: [f32; 4],
-        border_radius: u32,
+        border_radius: BorderRadius,
+        border_colors: [[f32; 4]; 4],
+        border_style: BorderStyle,
     ) -> NodeId {
-        // todo block layout
-        let kind = if style.display == Display::Grid {
-            NodeKind::Grid
-        } else {
-            NodeKind::Flexbox
-        };
+        let kind = Self::kind_from_style(&style);
 
         let node = Node {
+            props_size: style.size,
             style,
             background_color,
-            border_radius: border_radius as f32,
+            border_radius,
+            border_colors,
+            border_style,
             kind,
             ..Node::default()
         };
 
-        let id = self.nodes.insert(node);
+        let id = self.nodes.insert(node);
+
+        id.into()
+    }
+
+    pub fn update_node(
+        &mut self,
+        node_id: NodeId,
+        style: Style,
+        background_color: [f32; 4],
+        border_radius: BorderRadius,
+        border_colors: [[f32; 4]; 4],
+        border_style: BorderStyle,
+    ) {
+        let kind = Self::kind_from_style(&style);
+
+        self.set_with_transitions(node_id, &TransitionProperty::ANIMATABLE, |node| {
+            if node.text.is_none() && node.image.is_none() {
+                node.kind = kind;
+            }
+
+            // sizes set by animations stay until the props change them
+            let mut size = style.size;
+            if size.width == node.props_size.width {
+                size.width = node.style.size.width;
+            }
+            if size.height == node.props_size.height {
+                size.height = node.style.size.height;
+            }
+
+            node.props_size = style.size;
+            node.style = Style { size, ..style };
+            node.background_color = background_color;
+            node.border_radius = border_radius;
+            node.border_colors = border_colors;
+            node.border_style = border_style;
+        });
+
+        self.mark_dirty(node_id);
+        self.notify_update(node_id);
+    }
+
+    pub fn set_text(
+        &mut self,
+        node_id: NodeId,
+        content: &str,
+        color: [f32; 4],
+        font_size: f32,
+        line_height: f32,
+    ) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            let text = Text::new(
+                &mut self.text_system,
+                content,
+                color,
+                font_size,
+                line_height,
+            );
+
+            node.kind = NodeKind::Text;
+            node.text = Some(text);
+
+            self.mark_dirty(node_id);
+            self.notify_update(node_id);
+        }
+    }
+
+    pub fn reset_text(&mut self, node_id: NodeId) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.kind = Self::kind_from_style(&node.style);
+            node.text = None;
+
+            self.mark_dirty(node_id);
+            self.notify_update(node_id);
+        }
+    }
+
+    pub fn set_image(&mut self, node_id: NodeId, path: PathBuf, object_fit: ObjectFit) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            self.images.load(&path);
+
+            node.kind = NodeKind::Image;
+            let previous = node.image.replace(NodeImage { path, object_fit });
+
+            if previous.is_some() {
+                self.evict_images();
+            }
+
+            self.mark_dirty(node_id);
+            self.notify_update(node_id);
+        }
+    }
+
+    pub fn reset_image(&mut self, node_id: NodeId) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.kind = Self::kind_from_style(&node.style);
+            node.image = None;
+
+            self.evict_images();
+            self.mark_dirty(node_id);
+            self.notify_update(node_id);
+        }
+    }
+
+    /// Drops the decoded images that no node shows anymore
+    fn evict_images(&mut self) {
+        let used: HashSet<&Path> = self
+            .nodes
+            .values()
+            .filter_map(|node| node.image.as_ref())
+            .map(|image| image.path.as_path())
+            .collect();
+
+        self.images.retain(|path| used.contains(path));
+    }
+
+    pub fn set_box_shadows(&mut self, node_id: NodeId, box_shadows: Vec<BoxShadow>) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.box_shadows = box_shadows;
+            self.notify_update(node_id);
+        }
+    }
+
+    pub fn set_background_gradients(&mut self, node_id: NodeId, gradients: Vec<Gradient>) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.background_gradients = gradients;
+            self.notify_update(node_id);
+        }
+    }
+
+    pub fn set_transform(&mut self, node_id: NodeId, transform: Transform) {
+        self.set_with_transitions(node_id, &[TransitionProperty::Transform], |node| {
+            node.transform = transform;
+        });
+        self.notify_update(node_id);
+    }
+
+    pub fn set_opacity(&mut self, node_id: NodeId, opacity: f32) {
+        self.set_with_transitions(node_id, &[TransitionProperty::Opacity], |node| {
+            node.opacity = opacity.clamp(0.0, 1.0);
+        });
+        self.notify_update(node_id);
+    }
+
+    /// Replaces the transitions the node runs when its properties change
+    pub fn set_transitions(&mut self, node_id: NodeId, transitions: Vec<Transition>) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.transitions = transitions;
+        }
+    }
+
+    /// Changes the node with `set`, and then moves those of `properties` its
+    /// transitions name back to where they were, animating them to the new
+    /// value instead. Transitions already running towards the new value
+    /// carry on.
+    fn set_with_transitions(
+        &mut self,
+        node_id: NodeId,
+        properties: &[TransitionProperty],
+        set: impl FnOnce(&mut Node),
+    ) {
+        let Some(node) = self.nodes.get_mut(node_id.into()) else {
+            return;
+        };
+
+        let transitions: Vec<(AnimatedValue, Timing)> = properties
+            .iter()
+            .filter_map(|property| {
+                let timing = node.transition_timing(*property)?;
+                Some((node.transition_value(*property), timing))
+            })
+            .collect();
+
+        set(node);
+
+        let mut started = Vec::new();
+
+        for (from, timing) in transitions {
+            let to = node.animated_value(&from);
+            let running = self
+                .animations
+                .iter()
+                .any(|animation| animation.animates(node_id, &to) && *animation.target() == to);
+
+            if from != to && !running {
+                started.push((to, timing));
+            }
+            node.set_animated_value(from);
+        }
+
+        for (to, timing) in started {
+            self.start_animation(node_id, to, timing);
+        }
+    }
+
+    /// Animates a property of the node from where it is now to `to`,
+    /// cancelling the animation already running on it. None when the node
+    /// does not exist.
+    pub fn start_animation(
+        &mut self,
+        node_id: NodeId,
+        to: AnimatedValue,
+        timing: Timing,
+    ) -> Option<u32> {
+        let from = self.nodes.get(node_id.into())?.animated_value(&to);
+
+        let id = self.next_animation_id;
+        self.next_animation_id = self.next_animation_id.wrapping_add(1);
+
+        let (cancelled, running) = std::mem::take(&mut self.animations)
+            .into_iter()
+            .partition(|animation| animation.animates(node_id, &to));
+        self.animations = running;
+        self.cancel_animations(cancelled);
+
+        self.animations
+            .push(Animation::new(id, node_id, from, to, timing));
+        self.notify_update(node_id);
+        Some(id)
+    }
+
+    /// Leaves the property where the animation got to
+    pub fn stop_animation(&mut self, id: u32) {
+        let (cancelled, running): (Vec<Animation>, Vec<Animation>) =
+            std::mem::take(&mut self.animations)
+                .into_iter()
+                .partition(|animation| animation.id == id);
+        let node_id = cancelled.first().map(|animation| animation.node_id);
+        self.animations = running;
+        self.cancel_animations(cancelled);
+
+        // the animation ends with the next frame of its window
+        if let Some(node_id) = node_id {
+            self.notify_update(node_id);
+        }
+    }
+
+    fn cancel_animations(&mut self, animations: Vec<Animation>) {
+        self.finished_animations.extend(
+            animations
+                .into_iter()
+                .map(|animation| (animation.id, false)),
+        );
+    }
+
+    /// Ends the animations of nodes that are not in any window anymore,
+    /// which none would move on
+    fn cancel_orphaned_animations(&mut self) {
+        let (cancelled, running) = std::mem::take(&mut self.animations)
+            .into_iter()
+            .partition(|animation| self.window_root(animation.node_id).is_none());
+        self.animations = running;
+        self.cancel_animations(cancelled);
+    }
+
+    /// Whether animations run in the window of the root
+    pub fn is_animating(&self, root: NodeId) -> bool {
+        self.animations
+            .iter()
+            .any(|animation| self.window_root(animation.node_id) == Some(root))
+    }
+
+    /// Moves the animations in the window of the root on to `now`. Returns
+    /// whether a size changed, so the layout has to be computed again.
+    pub fn advance_animations(&mut self, root: NodeId, now: Instant) -> bool {
+        let mut layout_changed = false;
+
+        for animation in std::mem::take(&mut self.animations) {
+            let Some(animation_root) = self.window_root(animation.node_id) else {
+                // the node was removed, or is not in any window
+                self.finished_animations.push((animation.id, false));
+                continue;
+            };
+
+            if animation_root != root {
+                self.animations.push(animation);
+                continue;
+            }
+
+            let node = self.node_from_id_mut(animation.node_id);
+
+            let (value, done) = animation.sample(now, node.layout.size);
+            let affects_layout = value.affects_layout();
+            node.set_animated_value(value);
+
+            if affects_layout {
+                self.mark_dirty(animation.node_id);
+                layout_changed = true;
+            }
+
+            if done {
+                self.finished_animations.push((animation.id, true));
+            } else {
+                self.animations.push(animation);
+            }
+        }
+
+        layout_changed
+    }
+
+    pub fn take_finished_animations(&mut self) -> Vec<(u32, bool)> {
+        std::mem::take(&mut self.finished_animations)
+    }
+
+    /// Scrolls the innermost node under (x, y) that can still move by the
+    /// delta, passing it on to the ancestors of nodes that are already at
+    /// the end. Returns the node that scrolled with its new offset.
+    pub fn scroll_by(
+        &mut self,
+        root: NodeId,
+        x: f32,
+        y: f32,
+        delta_x: f32,
+        delta_y: f32,
+    ) -> Option<(NodeId, Point<f32>)> {
+        let target = self.hit_test(root, x, y)?;
+
+        for node_id in self.ancestors(target) {
+            let node = self.node_from_id_mut(node_id);
+            let offset = Point {
+                x: node.scroll_offset.x + delta_x,
+                y: node.scroll_offset.y + delta_y,
+            };
+
+            if node.set_scroll_offset(offset) {
+                let offset = node.scroll_offset;
+                self.notify_update(node_id);
+                return Some((node_id, offset));
+            }
+        }
+
+        None
+    }
+
+    /// Scrolls the node to the given offsets, keeping the current offset
+    /// on axes that are None. Returns the new offset when it changed.
+    pub fn scroll_to(
+        &mut self,
+        node_id: NodeId,
+        left: Option<f32>,
+        top: Option<f32>,
+    ) -> Option<Point<f32>> {
+        let node = self.nodes.get_mut(node_id.into())?;
+        let offset = Point {
+            x: left.unwrap_or(node.scroll_offset.x),
+            y: top.unwrap_or(node.scroll_offset.y),
+        };
+
+        if !node.set_scroll_offset(offset) {
+            return None;
+        }
+
+        let offset = node.scroll_offset;
+        self.notify_update(node_id);
+        Some(offset)
+    }
+
+    pub fn set_z_index(&mut self, node_id: NodeId, z_index: Option<i32>) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.z_index = z_index;
+            self.notify_update(node_id);
+        }
+    }
+
+    pub fn set_overlay_scrollbar_width(&mut self, node_id: NodeId, width: f32) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            if node.overlay_scrollbar_width != width {
+                node.overlay_scrollbar_width = width;
+                self.notify_update(node_id);
+            }
+        }
+    }
+
+    pub fn set_focusable(&mut self, node_id: NodeId, focusable: bool) {
+        if let Some(node) = self.nodes.get_mut(node_id.into()) {
+            node.focusable = focusable;
+        }
+    }
+
+    pub fn focused(&self) -> Option<NodeId> {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, node_id: Option<NodeId>) {
+        self.focused = node_id;
+    }
+
+    pub fn focusable_ancestor(&self, node_id: NodeId) -> Option<NodeId> {
+        self.ancestors(node_id)
+            .into_iter()
+            .find(|id| self.node_from_id(*id).focusable)
+    }
+
+    /// The focusable node after (or before) the focused one in tree order
+    /// in the window of the root, wrapping around at the ends
+    pub fn next_focusable(&self, root: NodeId, reverse: bool) -> Option<NodeId> {
+        fn collect_focusable(gui: &Gui, node_id: NodeId, focusable: &mut Vec<NodeId>) {
+            let node = gui.node_from_id(node_id);
+
+            if node.is_hidden() {
+                return;
+            }
+
+            if node.focusable {
+                focusable.push(node_id);
+            }
+
+            for child_id in gui.children_from_id(node_id) {
+                collect_focusable(gui, *child_id, focusable);
+            }
+        }
+
+        let mut focusable = Vec::new();
+        collect_focusable(self, root, &mut focusable);
+
+        if focusable.is_empty() {
+            return None;
+        }
+
+        let count = focusable.len();
+        let current = self
+            .focused
+            .and_then(|focused| focusable.iter().position(|id| *id == focused));
+
+        let index = match (current, reverse) {
+            (Some(index), false) => (index + 1) % count,
+            (Some(index), true) => (index + count - 1) % count,
+            (None, false) => 0,
+            (None, true) => count - 1,
+        };
+
+        Some(focusable[index])
+    }
+
+    pub fn text_system_mut(&mut self) -> &mut TextSystem {
+        &mut self.text_system
+    }
+
+    fn kind_from_style(style: &Style) -> NodeKind {
+        match style.display {
+            Display::Block => NodeKind::Block,
+            Display::Grid => NodeKind::Grid,
+            // hidden nodes skip the layout, the kind only matters once shown
+            Display::Flex | Display::None => NodeKind::Flexbox,
+        }
+    }
+
+    pub fn append_child_to_root(&mut self, child_id: NodeId) -> () {
+        self.append_child(self.root, child_id);
+    }
+
+    pub fn append_child(&mut self, parent_id: NodeId, child_id: NodeId) {
+        if !self.nodes.contains_key(parent_id.into()) {
+            return;
+        }
+
+        self.detach(child_id);
+
+        if let Some(parent) = self.nodes.get_mut(parent_id.into()) {
+            parent.append_child(child_id);
+        }
+
+        self.attach(parent_id, child_id);
+        self.notify_update(parent_id);
+    }
+
+    pub fn insert_before_in_root(&mut self, child_id: NodeId, before_id: NodeId) {
+        self.insert_before(self.root, child_id, before_id);
+    }
+
+    pub fn insert_before(&mut self, parent_id: NodeId, child_id: NodeId, before_id: NodeId) {
+        if !self.nodes.contains_key(parent_id.into()) {
+            return;
+        }
+
+        // React uses insertBefore to move existing children, too
+        self.detach(child_id);
+
+        if let Some(parent) = self.nodes.get_mut(parent_id.into()) {
+            parent.insert_child_before(child_id, before_id);
+        }
+
+        self.attach(parent_id, child_id);
+        self.notify_update(parent_id);
+    }
+
+    pub fn remove_child_from_root(&mut self, child_id: NodeId) {
+        self.remove_child(self.root, child_id);
+    }
+
+    /// Frees the child and its subtree, unless it is not a child of the
+    /// parent
+    pub fn remove_child(&mut self, parent_id: NodeId, child_id: NodeId) {
+        let removed = self
+            .nodes
+            .get_mut(parent_id.into())
+            .is_some_and(|parent| parent.remove_child(child_id));
+
+        if removed {
+            self.mark_dirty(parent_id);
+            self.remove_subtree(child_id);
+            self.evict_images();
+            self.cancel_orphaned_animations();
+            self.notify_update(parent_id);
+        }
+    }
+
+    fn attach(&mut self, parent_id: NodeId, child_id: NodeId) {
+        if let Some(child) = self.nodes.get_mut(child_id.into()) {
+            child.parent = Some(parent_id);
+        }
+        self.mark_dirty(parent_id);
+    }
+
+    fn detach(&mut self, child_id: NodeId) {
+        let parent_id = match self.nodes.get_mut(child_id.into()) {
+            Some(child) => child.parent.take(),
+            None => None,
+        };
 
-        id.into()
+        if let Some(parent_id) = parent_id {
+            if let Some(parent) = self.nodes.get_mut(parent_id.into()) {
+                parent.remove_child(child_id);
+            }
+            self.mark_dirty(parent_id);
+        }
     }
 
-    pub fn append_child_to_root(&mut self, child_id: NodeId) -> () {
-        self.append_child(self.root, child_id);
-        self.notify_update();
+    fn remove_subtree(&mut self, node_id: NodeId) {
+        if self.focused == Some(node_id) {
+            self.focused = None;
+        }
+
+        if let Some(node) = self.nodes.remove(node_id.into()) {
+            for child_id in node.children {
+                self.remove_subtree(child_id);
+            }
+        }
     }
 
-    pub fn append_child(&mut self, parent_id: NodeId, child_id: NodeId) {
-        if let Some(parent) = self.nodes.get_mut(parent_id.into()) {
-            parent.append_child(child_id);
-            self.notify_update();
+    /// Clears the layout cache of a node and all of its ancestors
+    fn mark_dirty(&mut self, node_id: NodeId) {
+        let mut current = Some(node_id);
+
+        while let Some(id) = current {
+            match self.nodes.get_mut(id.into()) {
+                Some(node) => {
+                    node.cache.clear();
+                    current = node.parent;
+                }
+                None => break,
+            }
         }
     }
 
@@ -140,60 +1381,486 @@ impl Gui {
         &self.nodes.get(node_id.into()).unwrap().layout
     }
 
+    /// Removes every node and closes the windows opened by the script, the
+    /// main window stays with a new root
     pub fn clear(&mut self) {
+        let opened: Vec<NodeId> = self
+            .windows
+            .keys()
+            .map(NodeId::from)
+            .filter(|root| *root != self.root)
+            .collect();
+
+        for root in opened {
+            self.close_window(root);
+        }
+
+        let scale_factor = self.scale_factor(self.root);
+
         self.nodes.clear();
-        self.root = self.nodes.insert(Self::create_root()).into();
-        self.notify_update();
+        self.windows.clear();
+        self.focused = None;
+        self.animations.clear();
+        self.finished_animations.clear();
+        self.evict_images();
+
+        let root = self.nodes.insert(Self::create_root());
+        self.windows.insert(root, WindowRoot { scale_factor });
+        self.root = root.into();
+        self.notify_update(self.root);
+    }
+
+    /// Whether the node exists. Ids of removed nodes never match a node again,
+    /// which also holds for those kept from before `clear`.
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.nodes.contains_key(node_id.into())
+    }
+
+    /// Adds a root that the app shows in a new window. Returns the root,
+    /// which nodes are appended to like to any other.
+    pub fn open_window(&mut self, options: WindowOptions) -> NodeId {
+        let root = self.nodes.insert(Self::create_root());
+        self.windows.insert(root, WindowRoot::default());
+
+        let root = NodeId::from(root);
+        if let Ok(proxy) = self.event_loop.lock() {
+            let _ = proxy.send_event(CustomEvent::OpenWindow { root, options });
+        }
+
+        root
+    }
+
+    /// Changes the options of the window of the root, the main one included
+    pub fn set_window_options(&self, root: NodeId, options: WindowOptions) {
+        if !self.windows.contains_key(root.into()) {
+            return;
+        }
+
+        if let Ok(proxy) = self.event_loop.lock() {
+            let _ = proxy.send_event(CustomEvent::SetWindowOptions { root, options });
+        }
+    }
+
+    /// Closes the window of a root opened with `open_window`, the main
+    /// window stays open
+    pub fn close_window(&mut self, root: NodeId) {
+        if root == self.root || self.windows.remove(root.into()).is_none() {
+            return;
+        }
+
+        // forgets the focus too when it was in the window
+        self.remove_subtree(root);
+        self.evict_images();
+        self.cancel_orphaned_animations();
+
+        if let Ok(proxy) = self.event_loop.lock() {
+            let _ = proxy.send_event(CustomEvent::CloseWindow(root));
+        }
+
+        // the main window tells the script about the ended animations
+        self.notify_update(self.root);
+    }
+
+    /// The root of the window showing the node, None while it is not
+    /// attached to one
+    pub fn window_root(&self, node_id: NodeId) -> Option<NodeId> {
+        let root = *self.ancestors(node_id).last()?;
+        self.windows.contains_key(root.into()).then_some(root)
+    }
+
+    pub fn scale_factor(&self, root: NodeId) -> f32 {
+        self.windows
+            .get(root.into())
+            .map_or(1.0, |window| window.scale_factor)
     }
 
-    pub fn compute_layout(&mut self, width: u32, height: u32) {
+    pub fn set_scale_factor(&mut self, root: NodeId, scale_factor: f32) {
+        if let Some(window) = self.windows.get_mut(root.into()) {
+            window.scale_factor = scale_factor;
+        }
+    }
+
+    /// Maps logical pixels into the physical pixels of the window
+    fn window_transform(&self, root: NodeId) -> Affine {
+        let scale_factor = self.scale_factor(root);
+        Affine::scale(scale_factor, scale_factor)
+    }
+
+    /// Lays out the tree below a root in a window of `width` by `height`
+    /// logical pixels
+    pub fn compute_layout(&mut self, root: NodeId, width: f32, height: f32) {
+        // nodes showing an image that finished loading need to be measured again
+        for path in self.images.receive() {
+            let node_ids: Vec<NodeId> = self
+                .nodes
+                .iter()
+                .filter(|(_, node)| node.image.as_ref().is_some_and(|image| image.path == path))
+                .map(|(key, _)| key.into())
+                .collect();
+
+            for node_id in node_ids {
+                self.mark_dirty(node_id);
+            }
+        }
+
         compute_root_layout(
             self,
-            NodeId::from(self.root),
+            root,
             Size {
-                width: length(width as f32),
-                height: length(height as f32),
+                width: length(width),
+                height: length(height),
             },
         );
+
+        // content that shrank can leave nodes scrolled past its end
+        for (_, node) in self.nodes.iter_mut() {
+            node.set_scroll_offset(node.scroll_offset);
+        }
     }
 
-    pub fn into_instances(&mut self) -> Vec<Instance> {
-        fn collect_instances(
+    /// The instances and texts of the tree below a root, in paint order
+    pub fn into_scene(&mut self, root: NodeId) -> Scene {
+        struct PaintState {
+            transform: Affine,
+            opacity: f32,
+            region: ClipRegion,
+            /// the text is cut off by the overflow of its own node too, and
+            /// scrolls along with the children
+            content_transform: Affine,
+            content_region: ClipRegion,
+        }
+
+        // transforms, opacity and clips are inherited down the tree, no
+        // matter the order the nodes are painted in
+        fn collect_paint_states(
             gui: &Gui,
             node_id: taffy::NodeId,
-            offset_x: f32,
-            offset_y: f32,
-            instances: &mut Vec<Instance>,
+            parent_transform: Affine,
+            parent_opacity: f32,
+            region: ClipRegion,
+            states: &mut SecondaryMap<DefaultKey, PaintState>,
+            clips: &mut Vec<Clip>,
         ) {
             let node = gui.node_from_id(node_id);
-            let (x, y) = (
-                offset_x + node.layout.location.x,
-                offset_y + node.layout.location.y,
+            let transform = parent_transform.multiply(node.local_transform());
+            let opacity = parent_opacity * node.opacity;
+
+            // nothing in a fully transparent subtree can show up again
+            if opacity <= 0.0 {
+                return;
+            }
+
+            let children_region = node.clip_children(transform, region, clips);
+            let children_transform = transform.multiply(node.scroll_transform());
+
+            states.insert(
+                node_id.into(),
+                PaintState {
+                    transform,
+                    opacity,
+                    region,
+                    content_transform: children_transform,
+                    content_region: children_region,
+                },
+            );
+
+            for child_id in gui.children_from_id(node_id) {
+                collect_paint_states(
+                    gui,
+                    *child_id,
+                    children_transform,
+                    opacity,
+                    children_region,
+                    states,
+                    clips,
+                );
+            }
+        }
+
+        fn push_node(gui: &Gui, node: &Node, state: &PaintState, scene: &mut Scene) {
+            let PaintState {
+                transform,
+                opacity,
+                region,
+                ..
+            } = *state;
+            let border = node.layout.border;
+            let radii = node.border_radius.resolve(node.layout.size);
+
+            // the first shadow is on top, so they are drawn in reverse
+            for shadow in node.box_shadows.iter().rev().filter(|shadow| !shadow.inset) {
+                scene.push(
+                    shadow
+                        .to_instance(node.layout.size, radii, border)
+                        .with_transform(transform, opacity),
+                    region,
+                );
+            }
+
+            // like css backgrounds, gradients are sized to the padding box
+            let padding_box = [
+                border.left,
+                border.top,
+                node.layout.size.width - border.left - border.right,
+                node.layout.size.height - border.top - border.bottom,
+            ];
+            let gradients = scene.push_gradients(
+                node.background_gradients
+                    .iter()
+                    .map(|gradient| gradient.resolve(padding_box)),
             );
-            let instance = Instance::new(
-                x,
-                y,
+
+            let mut instance = Instance::new(
+                0.0,
+                0.0,
                 node.layout.size.width,
                 node.layout.size.height,
                 node.background_color,
-                node.border_radius,
+                radii,
+            )
+            .with_gradients(gradients)
+            .with_border(
+                [border.top, border.right, border.bottom, border.left],
+                node.border_colors,
+                node.border_style,
+            )
+            .with_transform(transform, opacity);
+
+            let decoded = node
+                .image
+                .as_ref()
+                .and_then(|image| Some((image, gui.images.get(&image.path)?)));
+
+            match decoded {
+                Some((image, decoded)) => {
+                    let layout = &node.layout;
+                    let content_x = layout.padding.left + layout.border.left;
+                    let content_y = layout.padding.top + layout.border.top;
+
+                    let (pos, size, crop) = image.object_fit.place(
+                        [layout.content_box_width(), layout.content_box_height()],
+                        [decoded.width as f32, decoded.height as f32],
+                    );
+
+                    instance = instance.with_image([content_x + pos[0], content_y + pos[1]], size);
+                    let index = scene.push(instance, region);
+
+                    scene.push_image(ImageDraw {
+                        instance: index,
+                        image: decoded,
+                        crop,
+                    });
+                }
+                None => {
+                    scene.push(instance, region);
+                }
+            }
+
+            for shadow in node.box_shadows.iter().rev().filter(|shadow| shadow.inset) {
+                scene.push(
+                    shadow
+                        .to_instance(node.layout.size, radii, border)
+                        .with_transform(transform, opacity),
+                    region,
+                );
+            }
+        }
+
+        // glyphs are rasterized for the density of this window
+        let scale_factor = self.scale_factor(root);
+        self.text_system.set_scale_factor(scale_factor);
+
+        let mut scene = Scene::default();
+        let mut states = SecondaryMap::new();
+        collect_paint_states(
+            self,
+            root,
+            self.window_transform(root),
+            1.0,
+            ClipRegion::default(),
+            &mut states,
+            scene.clips_mut(),
+        );
+
+        for paint in paint_order(&self.nodes, root) {
+            match paint {
+                Paint::Node(node_id) => {
+                    if let Some(state) = states.get(node_id.into()) {
+                        push_node(self, self.node_from_id(node_id), state, &mut scene);
+                        self.push_text(
+                            node_id,
+                            state.content_transform,
+                            state.opacity,
+                            state.content_region,
+                            &mut scene,
+                        );
+                    }
+                }
+                // scrollbars stay in place on top of the content
+                Paint::Scrollbars(node_id) => {
+                    if let Some(state) = states.get(node_id.into()) {
+                        for thumb in self.node_from_id(node_id).scrollbar_thumbs() {
+                            scene.push(
+                                thumb.with_transform(state.transform, state.opacity),
+                                state.region,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        scene
+    }
+
+    /// Paints the text of a node over what is in the scene so far
+    fn push_text(
+        &mut self,
+        node_id: NodeId,
+        transform: Affine,
+        opacity: f32,
+        region: ClipRegion,
+        scene: &mut Scene,
+    ) {
+        let Some(node) = self.nodes.get_mut(node_id.into()) else {
+            return;
+        };
+        let layout = node.layout;
+
+        if let Some(text) = node.text.as_mut() {
+            let content_box = Affine::translate(
+                layout.padding.left + layout.border.left,
+                layout.padding.top + layout.border.top,
+            );
+
+            let mut glyphs = Vec::new();
+            text.collect_glyphs(
+                &mut self.text_system,
+                layout.content_box_width(),
+                transform.multiply(content_box),
+                opacity,
+                region,
+                &mut glyphs,
             );
+            scene.push_glyphs(glyphs);
+        }
+    }
+
+    /// The node under (x, y) in the window of the root
+    pub fn hit_test(&self, root: NodeId, x: f32, y: f32) -> Option<NodeId> {
+        // (x, y) in the coordinates of each node it can reach through the
+        // overflow clips of the ancestors
+        fn collect_local_points(
+            gui: &Gui,
+            node_id: NodeId,
+            parent_transform: Affine,
+            x: f32,
+            y: f32,
+            points: &mut SecondaryMap<DefaultKey, (f32, f32)>,
+        ) {
+            let node = gui.node_from_id(node_id);
+            let transform = parent_transform.multiply(node.local_transform());
 
-            instances.push(instance);
+            // a node scaled down to nothing has no area to hit, nor do its children
+            let Some(inverse) = transform.invert() else {
+                return;
+            };
 
-            for child_id in gui.children_from_id(node_id) {
-                collect_instances(gui, *child_id, x, y, instances);
+            let (local_x, local_y) = inverse.apply(x, y);
+            points.insert(node_id.into(), (local_x, local_y));
+
+            if node.clip_contains_point(local_x, local_y) {
+                let children_transform = transform.multiply(node.scroll_transform());
+
+                for child_id in gui.children_from_id(node_id) {
+                    collect_local_points(gui, *child_id, children_transform, x, y, points);
+                }
             }
         }
 
-        let mut instances = Vec::new();
-        collect_instances(&self, self.root, 0.0, 0.0, &mut instances);
-        return instances;
+        let mut points = SecondaryMap::new();
+        collect_local_points(self, root, Affine::IDENTITY, x, y, &mut points);
+
+        // nodes painted later are on top, so they win
+        paint_order(&self.nodes, root)
+            .into_iter()
+            .rev()
+            .find_map(|paint| match paint {
+                Paint::Node(node_id) => {
+                    let (local_x, local_y) = *points.get(node_id.into())?;
+                    let node = self.node_from_id(node_id);
+                    node.contains_point(local_x, local_y).then_some(node_id)
+                }
+                Paint::Scrollbars(_) => None,
+            })
+    }
+
+    /// Returns the node and its ancestors up to the root
+    pub fn ancestors(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut ancestors = Vec::new();
+        let mut current = Some(node_id);
+
+        while let Some(id) = current {
+            match self.nodes.get(id.into()) {
+                Some(node) => {
+                    ancestors.push(id);
+                    current = node.parent;
+                }
+                None => break,
+            }
+        }
+
+        ancestors
+    }
+
+    /// Returns the node and its ancestors up to the root, each with the
+    /// point (x, y) in its local coordinates
+    pub fn event_path(&self, node_id: NodeId, x: f32, y: f32) -> Vec<(NodeId, f32, f32)> {
+        let ancestors = self.ancestors(node_id);
+
+        let mut transform = Affine::IDENTITY;
+        let mut path = Vec::with_capacity(ancestors.len());
+
+        for id in ancestors.into_iter().rev() {
+            let node = self.node_from_id(id);
+            transform = transform.multiply(node.local_transform());
+            // nodes scaled down to nothing have no local coordinates to speak of
+            let (local_x, local_y) = transform
+                .invert()
+                .map_or((0.0, 0.0), |inverse| inverse.apply(x, y));
+            path.push((id, local_x, local_y));
+            transform = transform.multiply(node.scroll_transform());
+        }
+
+        path.reverse();
+        path
+    }
+
+    pub fn common_ancestor(&self, a: NodeId, b: NodeId) -> Option<NodeId> {
+        let ancestors_of_a = self.ancestors(a);
+
+        self.ancestors(b)
+            .into_iter()
+            .find(|id| ancestors_of_a.contains(id))
     }
 
-    fn notify_update(&self) {
+    /// Asks the window to draw another frame and run the animation frame
+    /// callbacks with it
+    pub fn request_animation_frame(&self) {
         if let Ok(proxy) = self.event_loop.lock() {
-            proxy.send_event(CustomEvent::GuiUpdate).unwrap();
+            let _ = proxy.send_event(CustomEvent::AnimationFrame);
+        }
+    }
+
+    /// Asks the window showing the node to draw again, nodes that are not
+    /// attached to a window show nowhere yet
+    fn notify_update(&self, node_id: NodeId) {
+        let Some(root) = self.window_root(node_id) else {
+            return;
+        };
+
+        if let Ok(proxy) = self.event_loop.lock() {
+            let _ = proxy.send_event(CustomEvent::GuiUpdate(root));
         }
     }
 }
@@ -247,14 +1914,65 @@ impl taffy::LayoutPartialTree for Gui {
         compute_cached_layout(self, node_id, inputs, |gui, node_id, inputs| {
             let node = gui.node_from_id_mut(node_id);
 
+            if node.is_hidden() {
+                return compute_hidden_layout(gui, node_id);
+            }
+
             match node.kind {
+                NodeKind::Block => compute_block_layout(gui, node_id, inputs),
                 NodeKind::Flexbox => compute_flexbox_layout(gui, node_id, inputs),
                 NodeKind::Grid => compute_grid_layout(gui, node_id, inputs),
+                NodeKind::Text => {
+                    let Gui {
+                        nodes, text_system, ..
+                    } = gui;
+                    let node = nodes.get_mut(node_id.into()).unwrap();
+                    let text = node.text.as_mut().unwrap();
+
+                    compute_leaf_layout(inputs, &node.style, |known_dimensions, available_space| {
+                        text.measure(text_system, known_dimensions, available_space)
+                    })
+                }
+                NodeKind::Image => {
+                    let node = gui.node_from_id(node_id);
+                    let decoded = node
+                        .image
+                        .as_ref()
+                        .and_then(|image| gui.images.get(&image.path));
+
+                    // images that are still loading take no space until they arrive
+                    compute_leaf_layout(inputs, &node.style, |known_dimensions, _| {
+                        match decoded.as_ref() {
+                            Some(decoded) => decoded.measure(known_dimensions),
+                            None => known_dimensions.unwrap_or(Size::ZERO),
+                        }
+                    })
+                }
             }
         })
     }
 }
 
+impl taffy::LayoutBlockContainer for Gui {
+    type BlockContainerStyle<'a>
+        = &'a Style
+    where
+        Self: 'a;
+
+    type BlockItemStyle<'a>
+        = &'a Style
+    where
+        Self: 'a;
+
+    fn get_block_container_style(&self, node_id: NodeId) -> Self::BlockContainerStyle<'_> {
+        &self.node_from_id(node_id).style
+    }
+
+    fn get_block_child_style(&self, child_node_id: NodeId) -> Self::BlockItemStyle<'_> {
+        &self.node_from_id(child_node_id).style
+    }
+}
+
 impl taffy::LayoutFlexboxContainer for Gui {
     type FlexboxContainerStyle<'a>
         = &'a Style
@@ -328,3 +2046,336 @@ impl taffy::CacheTree for Gui {
         self.node_from_id_mut(node_id).cache.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::TransformFunction;
+
+    fn radius(width: LengthPercentage, height: LengthPercentage) -> Size<LengthPercentage> {
+        Size { width, height }
+    }
+
+    fn corners(
+        top_left: Size<LengthPercentage>,
+        top_right: Size<LengthPercentage>,
+        bottom_right: Size<LengthPercentage>,
+        bottom_left: Size<LengthPercentage>,
+    ) -> BorderRadius {
+        BorderRadius {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        }
+    }
+
+    fn assert_close(actual: [[f32; 2]; 4], expected: [[f32; 2]; 4]) {
+        let close = actual
+            .iter()
+            .flatten()
+            .zip(expected.iter().flatten())
+            .all(|(actual, expected)| (actual - expected).abs() < 1e-4);
+        assert!(close, "expected {expected:?}, got {actual:?}");
+    }
+
+    const SIZE: Size<f32> = Size {
+        width: 200.0,
+        height: 100.0,
+    };
+
+    #[test]
+    fn resolves_percentages_against_each_axis() {
+        let percent = radius(
+            LengthPercentage::Percent(0.1),
+            LengthPercentage::Percent(0.1),
+        );
+
+        assert_eq!(
+            corners(percent, percent, percent, percent).resolve(SIZE),
+            [[20.0, 10.0]; 4]
+        );
+    }
+
+    #[test]
+    fn keeps_elliptical_radii_apart() {
+        let zero = radius(LengthPercentage::Length(0.0), LengthPercentage::Length(0.0));
+        let ellipse = radius(
+            LengthPercentage::Length(30.0),
+            LengthPercentage::Length(10.0),
+        );
+
+        assert_eq!(
+            corners(ellipse, zero, zero, zero).resolve(SIZE),
+            [[30.0, 10.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn shrinks_all_radii_when_adjacent_ones_overlap() {
+        let zero = radius(LengthPercentage::Length(0.0), LengthPercentage::Length(0.0));
+        let large = radius(
+            LengthPercentage::Length(150.0),
+            LengthPercentage::Length(20.0),
+        );
+        let small = radius(
+            LengthPercentage::Length(100.0),
+            LengthPercentage::Length(20.0),
+        );
+
+        // the top edge needs 250 of 200 pixels, so every radius shrinks by 0.8
+        assert_close(
+            corners(large, small, zero, zero).resolve(SIZE),
+            [[120.0, 16.0], [80.0, 16.0], [0.0, 0.0], [0.0, 0.0]],
+        );
+    }
+
+    #[test]
+    fn shrinks_by_the_most_overlapping_edge() {
+        let wide = radius(
+            LengthPercentage::Length(200.0),
+            LengthPercentage::Length(25.0),
+        );
+        let tall = radius(
+            LengthPercentage::Length(50.0),
+            LengthPercentage::Length(150.0),
+        );
+        let zero = radius(LengthPercentage::Length(0.0), LengthPercentage::Length(0.0));
+
+        // the top needs 250 of 200 pixels, the right side 150 of 100
+        assert_close(
+            corners(wide, tall, zero, zero).resolve(SIZE),
+            [
+                [200.0 / 1.5, 25.0 / 1.5],
+                [50.0 / 1.5, 100.0],
+                [0.0, 0.0],
+                [0.0, 0.0],
+            ],
+        );
+    }
+
+    #[test]
+    fn clamps_negative_radii_and_empty_boxes_to_zero() {
+        let negative = radius(
+            LengthPercentage::Length(-10.0),
+            LengthPercentage::Percent(-0.5),
+        );
+        let percent = radius(
+            LengthPercentage::Percent(0.5),
+            LengthPercentage::Percent(0.5),
+        );
+
+        assert_eq!(
+            corners(negative, negative, negative, negative).resolve(SIZE),
+            [[0.0, 0.0]; 4]
+        );
+        assert_eq!(
+            corners(percent, percent, percent, percent).resolve(Size::zero()),
+            [[0.0, 0.0]; 4]
+        );
+    }
+
+    /// Adds a node over the children to the tree
+    fn add(nodes: &mut SlotMap<DefaultKey, Node>, node: Node, children: &[NodeId]) -> NodeId {
+        let node_id: NodeId = nodes
+            .insert(Node {
+                children: children.to_vec(),
+                ..node
+            })
+            .into();
+        for child_id in children {
+            nodes[(*child_id).into()].parent = Some(node_id);
+        }
+        node_id
+    }
+
+    fn z_index(z_index: i32) -> Node {
+        Node {
+            z_index: Some(z_index),
+            ..Node::default()
+        }
+    }
+
+    /// The nodes in the order they paint, without their scrollbars
+    fn painted(nodes: &SlotMap<DefaultKey, Node>, root: NodeId) -> Vec<NodeId> {
+        paint_order(nodes, root)
+            .into_iter()
+            .filter_map(|paint| match paint {
+                Paint::Node(node_id) => Some(node_id),
+                Paint::Scrollbars(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paints_equal_z_indices_in_tree_order() {
+        let mut nodes = SlotMap::new();
+        let first = add(&mut nodes, z_index(1), &[]);
+        let second = add(&mut nodes, z_index(1), &[]);
+        let plain = add(&mut nodes, Node::default(), &[]);
+        let root = add(&mut nodes, Node::default(), &[first, second, plain]);
+
+        assert_eq!(painted(&nodes, root), [root, plain, first, second]);
+    }
+
+    #[test]
+    fn paints_negative_z_indices_below_the_content() {
+        let mut nodes = SlotMap::new();
+        let plain = add(&mut nodes, Node::default(), &[]);
+        let above = add(&mut nodes, z_index(0), &[]);
+        let below = add(&mut nodes, z_index(-1), &[]);
+        let bottom = add(&mut nodes, z_index(-2), &[]);
+        let root = add(&mut nodes, Node::default(), &[plain, above, below, bottom]);
+
+        assert_eq!(painted(&nodes, root), [root, bottom, below, plain, above]);
+    }
+
+    #[test]
+    fn paints_children_of_plain_nodes_in_the_parent_context() {
+        let mut nodes = SlotMap::new();
+        let child = add(&mut nodes, z_index(-1), &[]);
+        let plain = add(&mut nodes, Node::default(), &[child]);
+        let root = add(&mut nodes, Node::default(), &[plain]);
+
+        assert_eq!(painted(&nodes, root), [root, child, plain]);
+    }
+
+    #[test]
+    fn keeps_z_indices_inside_translucent_nodes() {
+        let mut nodes = SlotMap::new();
+        let child = add(&mut nodes, z_index(-1), &[]);
+        let translucent = add(
+            &mut nodes,
+            Node {
+                opacity: 0.5,
+                ..Node::default()
+            },
+            &[child],
+        );
+        let sibling = add(&mut nodes, Node::default(), &[]);
+        let root = add(&mut nodes, Node::default(), &[translucent, sibling]);
+
+        assert_eq!(painted(&nodes, root), [root, translucent, child, sibling]);
+    }
+
+    #[test]
+    fn keeps_z_indices_inside_transformed_nodes() {
+        let mut nodes = SlotMap::new();
+        let child = add(&mut nodes, z_index(2), &[]);
+        let transformed = add(
+            &mut nodes,
+            Node {
+                transform: Transform {
+                    functions: vec![TransformFunction::Rotate(0.5)],
+                    ..Transform::default()
+                },
+                ..Node::default()
+            },
+            &[child],
+        );
+        let sibling = add(&mut nodes, z_index(1), &[]);
+        let root = add(&mut nodes, Node::default(), &[transformed, sibling]);
+
+        assert_eq!(painted(&nodes, root), [root, transformed, child, sibling]);
+    }
+
+    #[test]
+    fn paints_scrollbars_over_the_content_below_positive_z_indices() {
+        let mut nodes = SlotMap::new();
+        let plain = add(&mut nodes, Node::default(), &[]);
+        let above = add(&mut nodes, z_index(1), &[]);
+        let root = add(&mut nodes, Node::default(), &[above, plain]);
+
+        let order: Vec<_> = paint_order(&nodes, root)
+            .into_iter()
+            .map(|paint| match paint {
+                Paint::Node(node_id) => (node_id, false),
+                Paint::Scrollbars(node_id) => (node_id, true),
+            })
+            .collect();
+
+        assert_eq!(
+            order,
+            [
+                (root, false),
+                (plain, false),
+                (plain, true),
+                (root, true),
+                (above, false),
+                (above, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_hidden_nodes_and_their_children() {
+        let mut nodes = SlotMap::new();
+        let child = add(&mut nodes, z_index(1), &[]);
+        let hidden = add(
+            &mut nodes,
+            Node {
+                style: Style {
+                    display: Display::None,
+                    ..Style::default()
+                },
+                ..Node::default()
+            },
+            &[child],
+        );
+        let root = add(&mut nodes, Node::default(), &[hidden]);
+
+        assert_eq!(painted(&nodes, root), [root]);
+    }
+
+    /// A node scrolling vertically, 100 pixels square, over content of the
+    /// height
+    fn scroller(content_height: f32, gutter: f32) -> Node {
+        let mut layout = Layout::with_order(0);
+        layout.size = Size {
+            width: 100.0,
+            height: 100.0,
+        };
+        layout.content_size = Size {
+            width: 100.0,
+            height: content_height,
+        };
+        layout.scrollbar_size.width = gutter;
+
+        Node {
+            style: Style {
+                overflow: Point {
+                    x: Overflow::Visible,
+                    y: Overflow::Scroll,
+                },
+                ..Style::default()
+            },
+            overlay_scrollbar_width: 10.0,
+            layout,
+            ..Node::default()
+        }
+    }
+
+    fn bytes(thumbs: &[Instance]) -> Vec<u8> {
+        bytemuck::cast_slice(thumbs).to_vec()
+    }
+
+    #[test]
+    fn overlays_scrollbars_only_while_the_content_overflows() {
+        assert!(scroller(100.0, 0.0).scrollbar_thumbs().is_empty());
+
+        let (start, length) = thumb_extent(100.0, 200.0, 0.0);
+        assert_eq!(
+            bytes(&scroller(300.0, 0.0).scrollbar_thumbs()),
+            bytes(&[scrollbar_thumb(90.0, start, 10.0, length)])
+        );
+    }
+
+    #[test]
+    fn keeps_scrollbars_in_reserved_gutters() {
+        let (start, length) = thumb_extent(100.0, 200.0, 0.0);
+        assert_eq!(
+            bytes(&scroller(300.0, 6.0).scrollbar_thumbs()),
+            bytes(&[scrollbar_thumb(94.0, start, 6.0, length)])
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::app::CustomEvent;
+use crate::gpu::GpuError;
+use crate::gpu::OffscreenGpu;
+use crate::gui::Gui;
+use crate::javascript_runtime::ScriptEvent;
+
+/// Logical size of the frame when none is given, the usual default size of
+/// a window
+pub const DEFAULT_SIZE: [f32; 2] = [800.0, 600.0];
+
+/// Time between animation frames
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// How long the gui has to stay the same after the script loaded for a run
+/// without watching to be done
+const IDLE_TIME: Duration = Duration::from_millis(500);
+
+/*
+ * Runs the script without a window: the main root is laid out in a fixed
+ * size and drawn into a texture each time it changes, saved as png when
+ * there is an output. Windows opened by the script are not drawn.
+ */
+pub struct Headless {
+    gui: Arc<Mutex<Gui>>,
+    gpu: OffscreenGpu,
+    /// Logical size the main root is laid out in, which is also the size
+    /// of the frame in pixels
+    size: [f32; 2],
+    events: Receiver<CustomEvent>,
+    script_events: Sender<ScriptEvent>,
+    output: Option<PathBuf>,
+    /// Whether the script reloads when it changes, otherwise the run ends
+    /// once the gui stays the same for `IDLE_TIME`
+    watch: bool,
+    started: Instant,
+    failed: bool,
+}
+
+impl Headless {
+    pub fn new(
+        gui: Arc<Mutex<Gui>>,
+        events: Receiver<CustomEvent>,
+        script_events: Sender<ScriptEvent>,
+        size: [f32; 2],
+        backends: wgpu::Backends,
+    ) -> Result<Self, GpuError> {
+        let gpu = OffscreenGpu::new(size[0].ceil() as u32, size[1].ceil() as u32, backends)?;
+
+        Ok(Self {
+            gui,
+            gpu,
+            size,
+            events,
+            script_events,
+            output: None,
+            watch: true,
+            started: Instant::now(),
+            failed: false,
+        })
+    }
+
+    pub fn with_output(mut self, output: Option<PathBuf>) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    pub fn run(mut self) -> ExitCode {
+        let mut loaded = false;
+        let mut changed = false;
+        let mut animation_frame_requested = false;
+        let mut last_frame = Instant::now();
+
+        loop {
+            let animating = animation_frame_requested
+                || self.gui.lock().is_ok_and(|gui| gui.is_animating(gui.root));
+
+            let timeout = if animating {
+                Some(FRAME_TIME.saturating_sub(last_frame.elapsed()))
+            } else if loaded && !self.watch {
+                Some(IDLE_TIME)
+            } else {
+                None
+            };
+
+            let event = match timeout {
+                Some(timeout) => self.events.recv_timeout(timeout),
+                None => self
+                    .events
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match event {
+                Ok(CustomEvent::GuiUpdate(root)) => {
+                    changed |= self.gui.lock().is_ok_and(|gui| gui.root == root);
+                }
+                Ok(CustomEvent::ImageLoaded) => changed = true,
+                Ok(CustomEvent::AnimationFrame) => animation_frame_requested = true,
+                Ok(CustomEvent::ScriptLoaded(ok)) => {
+                    if !ok && !self.watch {
+                        return ExitCode::FAILURE;
+                    }
+                    loaded = true;
+                    changed = true;
+                }
+                // only the main root is drawn
+                Ok(CustomEvent::OpenWindow { .. })
+                | Ok(CustomEvent::SetWindowOptions { .. })
+                | Ok(CustomEvent::CloseWindow(_)) => (),
+                Err(RecvTimeoutError::Timeout) => {
+                    if !animating {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if animating && last_frame.elapsed() >= FRAME_TIME {
+                last_frame = Instant::now();
+
+                // the callbacks ask for the frame after this one themselves
+                if animation_frame_requested {
+                    animation_frame_requested = false;
+                    let timestamp = self.started.elapsed().as_secs_f64() * 1000.0;
+                    let _ = self
+                        .script_events
+                        .send(ScriptEvent::AnimationFrame(timestamp));
+                }
+
+                self.draw();
+                changed = false;
+            } else if changed && !animating {
+                self.draw();
+                changed = false;
+            }
+        }
+
+        if self.failed {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+
+    /// Lays out the main root, moving its animations on to this frame
+    /// first, and renders it
+    fn draw(&mut self) {
+        let Ok(mut gui) = self.gui.lock() else {
+            return;
+        };
+
+        let root = gui.root;
+        let [width, height] = self.size;
+
+        if gui.is_animating(root) {
+            gui.advance_animations(root, Instant::now());
+        }
+
+        gui.compute_layout(root, width, height);
+
+        let scene = gui.into_scene(root);
+        self.gpu.update_scene(scene, gui.text_system_mut());
+
+        for (id, finished) in gui.take_finished_animations() {
+            let _ = self
+                .script_events
+                .send(ScriptEvent::AnimationEnd(id, finished));
+        }
+
+        drop(gui);
+
+        match &self.output {
+            Some(output) => {
+                if let Err(error) = self.gpu.save_png(output) {
+                    eprintln!("Failed to save {}: {error}", output.display());
+                    self.failed = true;
+                }
+            }
+            None => {
+                if let Err(error) = self.gpu.render() {
+                    eprintln!("{error}");
+                    self.failed = true;
+                }
+            }
+        }
+    }
+}
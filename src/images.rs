@@ -0,0 +1,223 @@
+use image::imageops::FilterType;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use taffy::Size;
+
+use crate::app::CustomEvent;
+use crate::app::EventSender;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ObjectFit {
+    Fill,
+    Contain,
+    Cover,
+}
+
+impl ObjectFit {
+    pub fn from_css(value: &str) -> Self {
+        match value {
+            "contain" => ObjectFit::Contain,
+            "cover" => ObjectFit::Cover,
+            _ => ObjectFit::Fill,
+        }
+    }
+
+    /// Places an image of `image_size` in a box of `box_size`. Returns the
+    /// position and size of the drawn image relative to the box, and the
+    /// visible part of the image as `[x, y, width, height]` in 0..1
+    pub fn place(self, box_size: [f32; 2], image_size: [f32; 2]) -> ([f32; 2], [f32; 2], [f32; 4]) {
+        let [box_width, box_height] = box_size;
+        let [image_width, image_height] = image_size;
+
+        if image_width <= 0.0 || image_height <= 0.0 {
+            return ([0.0, 0.0], [0.0, 0.0], [0.0, 0.0, 0.0, 0.0]);
+        }
+
+        let scale_x = box_width / image_width;
+        let scale_y = box_height / image_height;
+
+        match self {
+            ObjectFit::Fill => ([0.0, 0.0], box_size, [0.0, 0.0, 1.0, 1.0]),
+            ObjectFit::Contain => {
+                let scale = scale_x.min(scale_y);
+                let size = [image_width * scale, image_height * scale];
+                let pos = [(box_width - size[0]) * 0.5, (box_height - size[1]) * 0.5];
+
+                (pos, size, [0.0, 0.0, 1.0, 1.0])
+            }
+            ObjectFit::Cover => {
+                let scale = scale_x.max(scale_y);
+                let crop_width = box_width / (image_width * scale);
+                let crop_height = box_height / (image_height * scale);
+                let crop = [
+                    (1.0 - crop_width) * 0.5,
+                    (1.0 - crop_height) * 0.5,
+                    crop_width,
+                    crop_height,
+                ];
+
+                ([0.0, 0.0], box_size, crop)
+            }
+        }
+    }
+}
+
+pub struct NodeImage {
+    pub path: PathBuf,
+    pub object_fit: ObjectFit,
+}
+
+pub struct DecodedImage {
+    pub path: PathBuf,
+    /// Intrinsic size, used for layout
+    pub width: u32,
+    pub height: u32,
+    /// Pixels to upload, downscaled to fit into the image atlas
+    pub pixels: RgbaImage,
+}
+
+impl DecodedImage {
+    /// Sizes a node from the intrinsic size, keeping the aspect ratio when
+    /// only one dimension is known
+    pub fn measure(&self, known_dimensions: Size<Option<f32>>) -> Size<f32> {
+        let width = self.width as f32;
+        let height = self.height as f32;
+
+        match (known_dimensions.width, known_dimensions.height) {
+            (Some(known_width), Some(known_height)) => Size {
+                width: known_width,
+                height: known_height,
+            },
+            (Some(known_width), None) => Size {
+                width: known_width,
+                height: known_width * height / width,
+            },
+            (None, Some(known_height)) => Size {
+                width: known_height * width / height,
+                height: known_height,
+            },
+            (None, None) => Size { width, height },
+        }
+    }
+}
+
+enum ImageState {
+    Loading,
+    Loaded(Arc<DecodedImage>),
+    Failed,
+}
+
+/*
+ * Decodes images on background threads and wakes up the event loop
+ * once they are ready to be laid out and uploaded
+ */
+pub struct ImageLoader {
+    images: HashMap<PathBuf, ImageState>,
+    sender: Sender<(PathBuf, Option<DecodedImage>)>,
+    receiver: Receiver<(PathBuf, Option<DecodedImage>)>,
+    event_loop: Arc<Mutex<EventSender>>,
+}
+
+impl ImageLoader {
+    pub const MAX_TEXTURE_SIZE: u32 = 2048;
+
+    pub fn new(event_loop: Arc<Mutex<EventSender>>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            images: HashMap::new(),
+            sender,
+            receiver,
+            event_loop,
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) {
+        if self.images.contains_key(path) {
+            return;
+        }
+
+        self.images.insert(path.to_path_buf(), ImageState::Loading);
+
+        let path = path.to_path_buf();
+        let sender = self.sender.clone();
+        let event_loop = self.event_loop.clone();
+
+        thread::spawn(move || {
+            let decoded = match image::open(&path) {
+                Ok(image) => {
+                    let (width, height) = (image.width(), image.height());
+                    let max_size = Self::MAX_TEXTURE_SIZE;
+
+                    let pixels = if width > max_size || height > max_size {
+                        image
+                            .resize(max_size, max_size, FilterType::Triangle)
+                            .to_rgba8()
+                    } else {
+                        image.to_rgba8()
+                    };
+
+                    Some(DecodedImage {
+                        path: path.clone(),
+                        width,
+                        height,
+                        pixels,
+                    })
+                }
+                Err(error) => {
+                    eprintln!("Failed to load image {}: {error}", path.display());
+                    None
+                }
+            };
+
+            if sender.send((path, decoded)).is_ok() {
+                if let Ok(proxy) = event_loop.lock() {
+                    let _ = proxy.send_event(CustomEvent::ImageLoaded);
+                }
+            }
+        });
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Arc<DecodedImage>> {
+        match self.images.get(path) {
+            Some(ImageState::Loaded(image)) => Some(image.clone()),
+            _ => None,
+        }
+    }
+
+    /// Forgets the images `keep` returns false for, they load again when
+    /// asked for
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+        self.images.retain(|path, _| keep(path));
+    }
+
+    /// Stores the images decoded since the last call and returns their paths
+    pub fn receive(&mut self) -> Vec<PathBuf> {
+        let mut received = Vec::new();
+
+        while let Ok((path, decoded)) = self.receiver.try_recv() {
+            // forgotten while it was loading
+            if !self.images.contains_key(&path) {
+                continue;
+            }
+
+            let state = match decoded {
+                Some(image) => ImageState::Loaded(Arc::new(image)),
+                None => ImageState::Failed,
+            };
+
+            self.images.insert(path.clone(), state);
+            received.push(path);
+        }
+
+        received
+    }
+}
@@ -0,0 +1,329 @@
+import { match, P } from "ts-pattern";
+import { splitTopLevel, transformFromCss } from "./taffy.ts";
+import type { TransformFunction } from "./taffy.ts";
+
+// @ts-expect-error not typed yet
+const start_animation = Deno.core.ops.op_start_animation;
+// @ts-expect-error not typed yet
+const stop_animation = Deno.core.ops.op_stop_animation;
+
+type RectId = number | bigint;
+
+export type Easing = "Linear" | { CubicBezier: [number, number, number, number] };
+
+export type Timing =
+  | { Tween: { duration: number; delay: number; easing: Easing } }
+  | { Spring: { stiffness: number; damping: number; mass: number; velocity: number; delay: number } };
+
+export type TransitionProperty =
+  | "All"
+  | "Opacity"
+  | "Transform"
+  | "BackgroundColor"
+  | "BorderColor"
+  | "BorderRadius"
+  | "Width"
+  | "Height"
+  | "MinWidth"
+  | "MinHeight"
+  | "MaxWidth"
+  | "MaxHeight"
+  | "FlexBasis"
+  | "FlexGrow"
+  | "FlexShrink"
+  | "Margin"
+  | "Padding"
+  | "BorderWidth"
+  | "Inset"
+  | "Gap";
+
+export interface Transition {
+  property: TransitionProperty;
+  timing: Timing;
+}
+
+type AnimatedValue =
+  | { Opacity: number }
+  | { Transform: TransformFunction[] }
+  | { BackgroundColor: string }
+  | { Width: number }
+  | { Height: number };
+
+// the properties rust can animate on its own, sizes are in pixels
+export interface AnimatedStyle {
+  opacity?: number;
+  transform?: string;
+  backgroundColor?: string;
+  width?: number | `${number}px`;
+  height?: number | `${number}px`;
+}
+
+// durations and delays in milliseconds, easings like css
+export interface TweenOptions {
+  type?: "tween";
+  duration?: number;
+  delay?: number;
+  easing?: string;
+}
+
+export interface SpringOptions {
+  type: "spring";
+  stiffness?: number;
+  damping?: number;
+  mass?: number;
+  velocity?: number;
+  delay?: number;
+}
+
+export type AnimationOptions = TweenOptions | SpringOptions;
+
+export interface AnimationHandle {
+  stop(): void;
+}
+
+const finishCallbacks = new Map<number, (finished: boolean) => void>();
+
+/**
+ * Animates the style of a node from its current values without rendering
+ * again. `onFinish` gets whether every property reached its value, which
+ * is false when one was stopped or replaced by another animation.
+ */
+export function animate(
+  id: RectId,
+  style: AnimatedStyle,
+  options: AnimationOptions = {},
+  onFinish?: (finished: boolean) => void
+): AnimationHandle {
+  const timing = toTiming(options);
+  const animationIds: number[] = toAnimatedValues(style).map(value => start_animation(id, value, timing));
+
+  let remaining = animationIds.length;
+  let allFinished = true;
+
+  if (remaining === 0) {
+    onFinish?.(true);
+  }
+
+  for (const animationId of animationIds) {
+    finishCallbacks.set(animationId, finished => {
+      allFinished &&= finished;
+      remaining -= 1;
+      if (remaining === 0) {
+        onFinish?.(allFinished);
+      }
+    });
+  }
+
+  return {
+    stop() {
+      animationIds.forEach(animationId => stop_animation(animationId));
+    },
+  };
+}
+
+// "ease-in-out", "cubic-bezier(0.4, 0, 0.2, 1)"
+export function easingFromCss(value: string): Easing {
+  return match<string, Easing>(value.trim())
+    .with("linear", () => "Linear")
+    .with("ease", () => ({ CubicBezier: [0.25, 0.1, 0.25, 1] }))
+    .with("ease-in", () => ({ CubicBezier: [0.42, 0, 1, 1] }))
+    .with("ease-out", () => ({ CubicBezier: [0, 0, 0.58, 1] }))
+    .with("ease-in-out", () => ({ CubicBezier: [0.42, 0, 0.58, 1] }))
+    .with(P.string.startsWith("cubic-bezier("), v => {
+      const [x1 = 0, y1 = 0, x2 = 1, y2 = 1] = v.slice("cubic-bezier(".length, -1).split(",").map(parseFloat);
+      // the time has to keep moving forward, so x stays within 0 and 1 like css
+      return { CubicBezier: [clamp01(x1), y1, clamp01(x2), y2] };
+    })
+    .otherwise(() => {
+      throw new Error(`Unknown easing "${value}"`);
+    });
+}
+
+// "opacity 200ms ease-out, transform 1s", with the longhands overriding the shorthand
+export function transitionsFromCss<T extends Record<string, unknown>>(css: T): Transition[] {
+  const { transition, transitionProperty, transitionDuration, transitionTimingFunction, transitionDelay } = css;
+  const items = transition === undefined ? [] : splitTopLevel(String(transition), ",").map(toTransitionItem);
+
+  const properties = transitionProperty === undefined ? items.map(item => item.property) : toList(transitionProperty);
+  const durations =
+    transitionDuration === undefined ? items.map(item => item.duration) : toList(transitionDuration).map(toMilliseconds);
+  const easings = transitionTimingFunction === undefined ? items.map(item => item.easing) : toList(transitionTimingFunction);
+  const delays = transitionDelay === undefined ? items.map(item => item.delay) : toList(transitionDelay).map(toMilliseconds);
+
+  // like css, shorter lists repeat to cover every property
+  return properties.flatMap((property, index) => {
+    const duration = Math.max(cycle(durations, index) ?? 0, 0);
+    const delay = cycle(delays, index) ?? 0;
+    const easing = easingFromCss(cycle(easings, index) ?? "ease");
+
+    if (duration + delay <= 0) {
+      return [];
+    }
+
+    return toTransitionProperties(property).map(property => ({
+      property,
+      timing: { Tween: { duration, delay, easing } },
+    }));
+  });
+}
+
+interface TransitionItem {
+  property: string;
+  duration: number;
+  easing: string;
+  delay: number;
+}
+
+// the first time is the duration and the second the delay, in any order with the rest
+function toTransitionItem(item: string): TransitionItem {
+  const tokens = splitTopLevel(item.trim(), " ").filter(token => token !== "");
+  const [duration = 0, delay = 0] = tokens.filter(isTime).map(toMilliseconds);
+  const easing = tokens.find(isEasing) ?? "ease";
+  const property = tokens.find(token => !isTime(token) && !isEasing(token)) ?? "all";
+
+  return { property, duration, easing, delay };
+}
+
+const transitionProperties: Record<string, TransitionProperty[]> = {
+  all: ["All"],
+  opacity: ["Opacity"],
+  transform: ["Transform"],
+  background: ["BackgroundColor"],
+  backgroundColor: ["BackgroundColor"],
+  border: ["BorderWidth", "BorderColor"],
+  borderColor: ["BorderColor"],
+  borderTopColor: ["BorderColor"],
+  borderRightColor: ["BorderColor"],
+  borderBottomColor: ["BorderColor"],
+  borderLeftColor: ["BorderColor"],
+  borderRadius: ["BorderRadius"],
+  borderTopLeftRadius: ["BorderRadius"],
+  borderTopRightRadius: ["BorderRadius"],
+  borderBottomRightRadius: ["BorderRadius"],
+  borderBottomLeftRadius: ["BorderRadius"],
+  borderWidth: ["BorderWidth"],
+  borderTopWidth: ["BorderWidth"],
+  borderRightWidth: ["BorderWidth"],
+  borderBottomWidth: ["BorderWidth"],
+  borderLeftWidth: ["BorderWidth"],
+  width: ["Width"],
+  height: ["Height"],
+  minWidth: ["MinWidth"],
+  minHeight: ["MinHeight"],
+  maxWidth: ["MaxWidth"],
+  maxHeight: ["MaxHeight"],
+  flex: ["FlexGrow", "FlexShrink", "FlexBasis"],
+  flexBasis: ["FlexBasis"],
+  flexGrow: ["FlexGrow"],
+  flexShrink: ["FlexShrink"],
+  margin: ["Margin"],
+  marginTop: ["Margin"],
+  marginRight: ["Margin"],
+  marginBottom: ["Margin"],
+  marginLeft: ["Margin"],
+  padding: ["Padding"],
+  paddingTop: ["Padding"],
+  paddingRight: ["Padding"],
+  paddingBottom: ["Padding"],
+  paddingLeft: ["Padding"],
+  inset: ["Inset"],
+  top: ["Inset"],
+  right: ["Inset"],
+  bottom: ["Inset"],
+  left: ["Inset"],
+  gap: ["Gap"],
+  rowGap: ["Gap"],
+  columnGap: ["Gap"],
+};
+
+// "background-color" and "backgroundColor" alike, css ignores the properties it cannot animate
+function toTransitionProperties(property: string): TransitionProperty[] {
+  const name = property.trim().replace(/-([a-z])/g, (_, letter: string) => letter.toUpperCase());
+  return transitionProperties[name] ?? [];
+}
+
+function toList(value: unknown): string[] {
+  return typeof value === "number" ? [String(value)] : splitTopLevel(String(value), ",").map(part => part.trim());
+}
+
+function cycle<T>(values: T[], index: number): T | undefined {
+  return values.length === 0 ? undefined : values[index % values.length];
+}
+
+// numbers are milliseconds already
+function toMilliseconds(value: string | number): number {
+  return match(value)
+    .with(P.number, v => v)
+    .with(P.string.endsWith("ms"), v => parseFloat(v))
+    .with(P.string.endsWith("s"), v => parseFloat(v) * 1000)
+    .with(P.string.regex(/^-?[\d.]+$/), v => parseFloat(v))
+    .otherwise(() => {
+      throw new Error(`Unknown time "${value}"`);
+    });
+}
+
+function isTime(token: string): boolean {
+  return /^-?[\d.]+m?s$/.test(token);
+}
+
+function isEasing(token: string): boolean {
+  return ["linear", "ease", "ease-in", "ease-out", "ease-in-out"].includes(token) || token.startsWith("cubic-bezier(");
+}
+
+function toTiming(options: AnimationOptions): Timing {
+  if (options.type === "spring") {
+    const { stiffness = 100, damping = 10, mass = 1, velocity = 0, delay = 0 } = options;
+    return { Spring: { stiffness, damping, mass, velocity, delay } };
+  }
+
+  const { duration = 300, delay = 0, easing = "ease" } = options;
+  return { Tween: { duration, delay, easing: easingFromCss(easing) } };
+}
+
+function toAnimatedValues(style: AnimatedStyle): AnimatedValue[] {
+  const values: AnimatedValue[] = [];
+
+  if (style.opacity !== undefined) {
+    values.push({ Opacity: style.opacity });
+  }
+  if (style.transform !== undefined) {
+    values.push({ Transform: transformFromCss({ transform: style.transform }).functions });
+  }
+  if (style.backgroundColor !== undefined) {
+    values.push({ BackgroundColor: style.backgroundColor });
+  }
+  if (style.width !== undefined) {
+    values.push({ Width: toPixels(style.width) });
+  }
+  if (style.height !== undefined) {
+    values.push({ Height: toPixels(style.height) });
+  }
+
+  return values;
+}
+
+// animations start from the laid out size, which is in pixels, so other
+// units like percentages cannot be mixed with it
+function toPixels(value: string | number): number {
+  if (typeof value === "number") {
+    return value;
+  }
+  if (!/^-?[\d.]+(px)?$/.test(value.trim())) {
+    throw new Error(`Unknown size "${value}", only pixels can be animated`);
+  }
+  return parseFloat(value);
+}
+
+function clamp01(value: number): number {
+  return Math.min(Math.max(value, 0), 1);
+}
+
+// called from rust once an animation ends, see `ScriptEvent::AnimationEnd`
+function finishAnimation(id: number, finished: boolean) {
+  const callback = finishCallbacks.get(id);
+  finishCallbacks.delete(id);
+  callback?.(finished);
+}
+
+// @ts-expect-error not typed yet
+globalThis.__finishAnimation = finishAnimation;
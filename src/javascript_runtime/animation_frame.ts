@@ -0,0 +1,54 @@
+// @ts-expect-error not typed yet
+const request_animation_frame = Deno.core.ops.op_request_animation_frame;
+
+type FrameRequestCallback = (timestamp: number) => void;
+
+declare global {
+  function requestAnimationFrame(callback: FrameRequestCallback): number;
+  function cancelAnimationFrame(handle: number): void;
+}
+
+let nextHandle = 1;
+
+// callbacks for the next frame, and those of the frame that is running
+let pendingCallbacks = new Map<number, FrameRequestCallback>();
+let runningCallbacks = new Map<number, FrameRequestCallback>();
+
+export function requestAnimationFrame(callback: FrameRequestCallback): number {
+  // the window only redraws while someone is waiting for a frame
+  if (pendingCallbacks.size === 0) {
+    request_animation_frame();
+  }
+
+  const handle = nextHandle++;
+  pendingCallbacks.set(handle, callback);
+  return handle;
+}
+
+export function cancelAnimationFrame(handle: number) {
+  pendingCallbacks.delete(handle);
+  runningCallbacks.delete(handle);
+}
+
+// callbacks requested while running go to the next frame, like in the dom
+function runAnimationFrame(timestamp: number) {
+  runningCallbacks = pendingCallbacks;
+  pendingCallbacks = new Map();
+
+  for (const callback of runningCallbacks.values()) {
+    try {
+      callback(timestamp);
+    } catch (error) {
+      console.error(error);
+    }
+  }
+
+  runningCallbacks = new Map();
+}
+
+globalThis.requestAnimationFrame = requestAnimationFrame;
+globalThis.cancelAnimationFrame = cancelAnimationFrame;
+
+// called from rust when a frame is drawn, see `ScriptEvent::AnimationFrame`
+// @ts-expect-error not typed yet
+globalThis.__runAnimationFrame = runAnimationFrame;
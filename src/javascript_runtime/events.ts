@@ -0,0 +1,219 @@
+type RectId = number | bigint;
+
+type PointerEventType = "pointerdown" | "pointerup" | "pointermove" | "click";
+type KeyboardEventType = "keydown" | "keyup";
+type FocusEventType = "focus" | "blur";
+type ScrollEventType = "scroll";
+type CloseEventType = "close";
+
+interface EventTarget {
+  id: number;
+  x: number;
+  y: number;
+}
+
+interface PointerEventPayload {
+  type: PointerEventType;
+  path: EventTarget[];
+  windowX: number;
+  windowY: number;
+  button: number;
+}
+
+interface KeyboardEventPayload {
+  type: KeyboardEventType;
+  path: number[];
+  key: string;
+  code: string;
+  repeat: boolean;
+  shiftKey: boolean;
+  ctrlKey: boolean;
+  altKey: boolean;
+  metaKey: boolean;
+}
+
+interface FocusEventPayload {
+  type: FocusEventType;
+  path: number[];
+}
+
+interface ScrollEventPayload {
+  type: ScrollEventType;
+  path: number[];
+  scrollLeft: number;
+  scrollTop: number;
+}
+
+interface CloseEventPayload {
+  type: CloseEventType;
+  path: number[];
+}
+
+type EventPayload =
+  | PointerEventPayload
+  | KeyboardEventPayload
+  | FocusEventPayload
+  | ScrollEventPayload
+  | CloseEventPayload;
+
+export interface GuiPointerEvent {
+  type: PointerEventType;
+  target: number;
+  currentTarget: number;
+  clientX: number;
+  clientY: number;
+  offsetX: number;
+  offsetY: number;
+  button: number;
+  stopPropagation(): void;
+}
+
+export interface GuiKeyboardEvent {
+  type: KeyboardEventType;
+  target: number;
+  currentTarget: number;
+  key: string;
+  code: string;
+  repeat: boolean;
+  shiftKey: boolean;
+  ctrlKey: boolean;
+  altKey: boolean;
+  metaKey: boolean;
+  stopPropagation(): void;
+}
+
+export interface GuiFocusEvent {
+  type: FocusEventType;
+  target: number;
+  currentTarget: number;
+  stopPropagation(): void;
+}
+
+export interface GuiScrollEvent {
+  type: ScrollEventType;
+  target: number;
+  currentTarget: number;
+  scrollLeft: number;
+  scrollTop: number;
+  stopPropagation(): void;
+}
+
+// the user closed a window, which stays gone even if it is not unmounted
+export interface GuiCloseEvent {
+  type: CloseEventType;
+  target: number;
+  currentTarget: number;
+  stopPropagation(): void;
+}
+
+const handlerNames: Record<EventPayload["type"], string> = {
+  pointerdown: "onPointerDown",
+  pointerup: "onPointerUp",
+  pointermove: "onPointerMove",
+  click: "onClick",
+  keydown: "onKeyDown",
+  keyup: "onKeyUp",
+  focus: "onFocus",
+  blur: "onBlur",
+  scroll: "onScroll",
+  close: "onClose",
+};
+
+// ids are bigints when returned from ops but plain numbers in events
+const listeners = new Map<number, Record<string, unknown>>();
+
+export function setListeners(id: RectId, props: Record<string, unknown>) {
+  listeners.set(Number(id), props);
+}
+
+export function removeListeners(id: RectId) {
+  listeners.delete(Number(id));
+}
+
+// windows the user closed, which rust removed before react unmounts them
+const closedWindows = new Set<number>();
+
+// whether the user closed the window of the root, forgetting it
+export function takeClosedWindow(id: RectId) {
+  return closedWindows.delete(Number(id));
+}
+
+function dispatchGuiEvent(event: EventPayload) {
+  switch (event.type) {
+    case "pointerdown":
+    case "pointerup":
+    case "pointermove":
+    case "click": {
+      const { path, windowX, windowY, button } = event;
+      return propagate(event.type, path, ({ x, y }) => ({
+        clientX: windowX,
+        clientY: windowY,
+        offsetX: x,
+        offsetY: y,
+        button,
+      }));
+    }
+    case "keydown":
+    case "keyup": {
+      const { type, path, ...keyboardEvent } = event;
+      return propagate(type, toTargets(path), () => keyboardEvent);
+    }
+    case "close": {
+      closedWindows.add(event.path[0]);
+      return propagate(event.type, toTargets(event.path), () => ({}));
+    }
+    case "focus":
+    case "blur": {
+      return propagate(event.type, toTargets(event.path), () => ({}));
+    }
+    case "scroll": {
+      const { type, path, scrollLeft, scrollTop } = event;
+      return propagate(type, toTargets(path), () => ({ scrollLeft, scrollTop }));
+    }
+  }
+}
+
+// for scrolling done from js, which rust does not report back
+export function dispatchScroll(id: RectId, [scrollLeft, scrollTop]: [number, number]) {
+  dispatchGuiEvent({ type: "scroll", path: [Number(id)], scrollLeft, scrollTop });
+}
+
+function propagate<T extends { id: number }>(
+  type: EventPayload["type"],
+  path: T[],
+  toEvent: (target: T) => Record<string, unknown>
+) {
+  const handlerName = handlerNames[type];
+  const [target] = path;
+  let propagationStopped = false;
+
+  for (const entry of path) {
+    const handler = listeners.get(entry.id)?.[handlerName];
+
+    if (typeof handler !== "function") {
+      continue;
+    }
+
+    handler({
+      ...toEvent(entry),
+      type,
+      target: target.id,
+      currentTarget: entry.id,
+      stopPropagation() {
+        propagationStopped = true;
+      },
+    });
+
+    if (propagationStopped) {
+      break;
+    }
+  }
+}
+
+function toTargets(path: number[]): { id: number }[] {
+  return path.map(id => ({ id }));
+}
+
+// called from rust, see `ScriptEvent::Dispatch`
+// @ts-expect-error not typed yet
+globalThis.__dispatchGuiEvent = dispatchGuiEvent;
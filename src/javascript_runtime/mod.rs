@@ -5,22 +5,76 @@ use taffy::prelude::*;
 
 use color::parse_color;
 use color::DynamicColor;
+use color::Oklab;
+use deno_core::error::ModuleLoaderError;
 use deno_core::extension;
 use deno_core::op2;
+use deno_core::serde_json::Value;
+use deno_core::ModuleSpecifier;
 use deno_core::OpState;
+use deno_core::RequestedModuleType;
 use deno_error::JsErrorBox;
-use notify::event::ModifyKind;
-use notify::{recommended_watcher, EventKind, RecursiveMode, Watcher};
-use rustyscript::{Error, Module, Runtime, RuntimeOptions};
+use rustyscript::module_loader::ImportProvider;
+use rustyscript::{json_args, Error, Module, Runtime, RuntimeOptions};
+use serde::Deserialize;
 use std::path::Path;
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 use taffy::NodeId;
+use taffy::Point;
 
+use crate::animation::AnimatedValue;
+use crate::animation::Timing;
+use crate::animation::Transition;
+use crate::app::CustomEvent;
+use crate::app::EventSender;
+use crate::events::JsEvent;
+use crate::gradient::ColorStop;
+use crate::gradient::Gradient;
+use crate::gradient::GradientKind;
+use crate::gui::BorderRadius;
+use crate::gui::BorderStyle;
+use crate::gui::BoxShadow;
 use crate::gui::Gui;
+use crate::images::ObjectFit;
+use crate::transform::Transform;
+use crate::transform::TransformFunction;
+use crate::window_options::WindowOptions;
+
+use self::module_watcher::ModuleWatcher;
+
+mod module_watcher;
+
+/// Editors may save a file in a few writes, the script reloads once they
+/// stopped for this long
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Directory of the entry script, image sources and window icons are
+/// resolved against it
+struct ScriptDir(PathBuf);
+
+/// The node of an id from the script, which may not exist anymore, like
+/// when kept from before a reload. That throws in the script instead of
+/// panicking in the gui.
+fn existing_node(gui: &Gui, id: usize) -> Result<NodeId, JsErrorBox> {
+    let node_id = NodeId::from(id);
+
+    if gui.contains(node_id) {
+        Ok(node_id)
+    } else {
+        Err(JsErrorBox::generic(format!(
+            "Node {id} does not exist, it was removed or is from before a reload"
+        )))
+    }
+}
 
 // op2 ref: https://docs.rs/rustjs/latest/rustjs/deno_core/attr.op2.html#parameters
 
@@ -32,47 +86,524 @@ fn op_create_instance(
     state: &mut OpState,
     #[serde] layout: Style,
     #[string] background_color: String,
-    border_radius: u32,
+    #[serde] border_radius: BorderRadius,
+    #[serde] border_colors: [String; 4],
+    #[string] border_style: String,
 ) -> Result<usize, JsErrorBox> {
-    let default_background: &str = "transparent";
-
-    let parsed_background_color = parse_color(&background_color)
-        .unwrap_or(DynamicColor::from_str(default_background).unwrap())
-        .components;
+    let parsed_background_color = parse_color_or(&background_color, "transparent").components;
+    let parsed_border_colors =
+        border_colors.map(|color| parse_color_or(&color, "black").components);
 
     let node_id = state
         .borrow::<Arc<Mutex<Gui>>>()
         .lock()
         .unwrap()
-        .create_node(layout, parsed_background_color, border_radius);
+        .create_node(
+            layout,
+            parsed_background_color,
+            border_radius,
+            parsed_border_colors,
+            BorderStyle::from_css(&border_style),
+        );
 
     Ok(usize::from(node_id))
 }
 
+#[op2]
+fn op_update_instance(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[serde] layout: Style,
+    #[string] background_color: String,
+    #[serde] border_radius: BorderRadius,
+    #[serde] border_colors: [String; 4],
+    #[string] border_style: String,
+) -> Result<(), JsErrorBox> {
+    let parsed_background_color = parse_color_or(&background_color, "transparent").components;
+    let parsed_border_colors =
+        border_colors.map(|color| parse_color_or(&color, "black").components);
+
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.update_node(
+        node_id,
+        layout,
+        parsed_background_color,
+        border_radius,
+        parsed_border_colors,
+        BorderStyle::from_css(&border_style),
+    );
+
+    Ok(())
+}
+
+#[op2]
+fn op_set_text(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[string] text: String,
+    #[string] color: String,
+    font_size: f32,
+    line_height: f32,
+) -> Result<(), JsErrorBox> {
+    let parsed_color = parse_color_or(&color, "black").components;
+
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_text(node_id, &text, parsed_color, font_size, line_height);
+
+    Ok(())
+}
+
 #[op2(fast)]
-fn op_append_child_to_container(
+fn op_reset_text(state: &mut OpState, #[bigint] node_id: usize) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.reset_text(node_id);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_reset_image(state: &mut OpState, #[bigint] node_id: usize) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.reset_image(node_id);
+
+    Ok(())
+}
+
+#[op2]
+fn op_set_image(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[string] src: String,
+    #[string] object_fit: String,
+) -> Result<(), JsErrorBox> {
+    let path = state.borrow::<ScriptDir>().0.join(src);
+
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_image(node_id, path, ObjectFit::from_css(&object_fit));
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct BoxShadowCss {
+    offset_x: f32,
+    offset_y: f32,
+    blur: f32,
+    spread: f32,
+    color: String,
+    inset: bool,
+}
+
+#[op2]
+fn op_set_box_shadows(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[serde] box_shadows: Vec<BoxShadowCss>,
+) -> Result<(), JsErrorBox> {
+    let parsed_box_shadows = box_shadows
+        .into_iter()
+        .map(|shadow| BoxShadow {
+            offset_x: shadow.offset_x,
+            offset_y: shadow.offset_y,
+            blur: shadow.blur,
+            spread: shadow.spread,
+            color: parse_color_or(&shadow.color, "black").components,
+            inset: shadow.inset,
+        })
+        .collect();
+
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_box_shadows(node_id, parsed_box_shadows);
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct ColorStopCss {
+    color: String,
+    position: Option<LengthPercentage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GradientCss {
+    kind: GradientKind,
+    stops: Vec<ColorStopCss>,
+}
+
+#[op2]
+fn op_set_background_gradients(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[serde] gradients: Vec<GradientCss>,
+) -> Result<(), JsErrorBox> {
+    let parsed_gradients = gradients
+        .into_iter()
+        .map(|gradient| Gradient {
+            kind: gradient.kind,
+            stops: gradient
+                .stops
+                .into_iter()
+                .map(|stop| ColorStop {
+                    // premultiplied oklab, which gradients are interpolated in
+                    color: parse_color_or(&stop.color, "transparent")
+                        .to_alpha_color::<Oklab>()
+                        .premultiply()
+                        .components,
+                    position: stop.position,
+                })
+                .collect(),
+        })
+        .collect();
+
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_background_gradients(node_id, parsed_gradients);
+
+    Ok(())
+}
+
+#[op2]
+fn op_set_transform(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[serde] functions: Vec<TransformFunction>,
+    #[serde] origin: Point<LengthPercentage>,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_transform(node_id, Transform { functions, origin });
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_set_opacity(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    opacity: f32,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_opacity(node_id, opacity);
+
+    Ok(())
+}
+
+/// `z_index` is null for `auto`
+#[op2]
+fn op_set_z_index(
     state: &mut OpState,
     #[bigint] node_id: usize,
+    #[serde] z_index: Option<i32>,
 ) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_z_index(node_id, z_index);
+
+    Ok(())
+}
+
+/// Returns the new `[scrollLeft, scrollTop]`, or null when the node did not
+/// move
+#[op2]
+#[serde]
+fn op_scroll_to(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[serde] left: Option<f32>,
+    #[serde] top: Option<f32>,
+) -> Result<Option<[f32; 2]>, JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    let offset = gui.scroll_to(node_id, left, top);
+
+    Ok(offset.map(|offset| [offset.x, offset.y]))
+}
+
+#[derive(Debug, Deserialize)]
+enum AnimatedValueCss {
+    Opacity(f32),
+    Transform(Vec<TransformFunction>),
+    BackgroundColor(String),
+    Width(f32),
+    Height(f32),
+}
+
+/// Returns the id `__finishAnimation` is called with once the animation
+/// ends
+#[op2]
+fn op_start_animation(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    #[serde] to: AnimatedValueCss,
+    #[serde] timing: Timing,
+) -> Result<u32, JsErrorBox> {
+    if !timing.is_valid() {
+        return Err(JsErrorBox::generic(
+            "Springs need a stiffness and a mass above 0 and a damping of at least 0",
+        ));
+    }
+
+    let parsed_to = match to {
+        AnimatedValueCss::Opacity(opacity) => AnimatedValue::Opacity(opacity),
+        AnimatedValueCss::Transform(functions) => AnimatedValue::Transform(functions),
+        AnimatedValueCss::BackgroundColor(color) => {
+            AnimatedValue::BackgroundColor(parse_color_or(&color, "transparent").components)
+        }
+        AnimatedValueCss::Width(width) => AnimatedValue::Width(width),
+        AnimatedValueCss::Height(height) => AnimatedValue::Height(height),
+    };
+
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    let id = gui
+        .start_animation(node_id, parsed_to, timing)
+        .ok_or_else(|| JsErrorBox::generic("Failed to start the animation"))?;
+
+    Ok(id)
+}
+
+#[op2(fast)]
+fn op_stop_animation(state: &mut OpState, id: u32) -> Result<(), JsErrorBox> {
     state
         .borrow::<Arc<Mutex<Gui>>>()
         .lock()
         .unwrap()
-        .append_child_to_root(NodeId::from(node_id));
+        .stop_animation(id);
 
     Ok(())
 }
-#[op2(fast)]
-fn op_append_child(
+
+#[op2]
+fn op_set_transitions(
     state: &mut OpState,
-    #[bigint] parent_id: usize,
-    #[bigint] child_id: usize,
+    #[bigint] node_id: usize,
+    #[serde] transitions: Vec<Transition>,
 ) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_transitions(node_id, transitions);
+
+    Ok(())
+}
+
+/// The window redraws and calls `__runAnimationFrame` for the next frame
+#[op2(fast)]
+fn op_request_animation_frame(state: &mut OpState) -> Result<(), JsErrorBox> {
     state
         .borrow::<Arc<Mutex<Gui>>>()
         .lock()
         .unwrap()
-        .append_child(NodeId::from(parent_id), NodeId::from(child_id));
+        .request_animation_frame();
+
+    Ok(())
+}
+
+/// Physical pixels per logical pixel of the main window
+#[op2(fast)]
+fn op_get_pixel_ratio(state: &mut OpState) -> Result<f32, JsErrorBox> {
+    let gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+
+    Ok(gui.scale_factor(gui.root))
+}
+
+/// Returns the root of the new window, which children are appended to
+#[op2]
+#[bigint]
+fn op_open_window(
+    state: &mut OpState,
+    #[serde] options: WindowOptions,
+) -> Result<usize, JsErrorBox> {
+    let options = resolve_icon(state, options);
+
+    let root = state
+        .borrow::<Arc<Mutex<Gui>>>()
+        .lock()
+        .unwrap()
+        .open_window(options);
+
+    Ok(usize::from(root))
+}
+
+#[op2]
+fn op_set_window_options(
+    state: &mut OpState,
+    #[bigint] root: usize,
+    #[serde] options: WindowOptions,
+) -> Result<(), JsErrorBox> {
+    let options = resolve_icon(state, options);
+    let gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let root = existing_node(&gui, root)?;
+
+    gui.set_window_options(root, options);
+
+    Ok(())
+}
+
+#[op2]
+fn op_set_main_window_options(
+    state: &mut OpState,
+    #[serde] options: WindowOptions,
+) -> Result<(), JsErrorBox> {
+    let options = resolve_icon(state, options);
+    let gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+
+    gui.set_window_options(gui.root, options);
+
+    Ok(())
+}
+
+fn resolve_icon(state: &OpState, options: WindowOptions) -> WindowOptions {
+    WindowOptions {
+        icon: options
+            .icon
+            .map(|icon| state.borrow::<ScriptDir>().0.join(icon)),
+        ..options
+    }
+}
+
+#[op2(fast)]
+fn op_close_window(state: &mut OpState, #[bigint] root: usize) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let root = existing_node(&gui, root)?;
+
+    gui.close_window(root);
+
+    Ok(())
+}
+
+/// `width` is 0 for nodes that do not scroll or reserve a gutter for their
+/// scrollbars
+#[op2(fast)]
+fn op_set_overlay_scrollbar_width(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    width: f32,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_overlay_scrollbar_width(node_id, width);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_set_focusable(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+    focusable: bool,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.set_focusable(node_id, focusable);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_append_child_to_container(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.append_child_to_root(node_id);
+
+    Ok(())
+}
+#[op2(fast)]
+fn op_append_child(
+    state: &mut OpState,
+    #[bigint] parent_id: usize,
+    #[bigint] child_id: usize,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let parent_id = existing_node(&gui, parent_id)?;
+    let child_id = existing_node(&gui, child_id)?;
+
+    gui.append_child(parent_id, child_id);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_insert_in_container_before(
+    state: &mut OpState,
+    #[bigint] child_id: usize,
+    #[bigint] before_id: usize,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let child_id = existing_node(&gui, child_id)?;
+    let before_id = existing_node(&gui, before_id)?;
+
+    gui.insert_before_in_root(child_id, before_id);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_insert_before(
+    state: &mut OpState,
+    #[bigint] parent_id: usize,
+    #[bigint] child_id: usize,
+    #[bigint] before_id: usize,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let parent_id = existing_node(&gui, parent_id)?;
+    let child_id = existing_node(&gui, child_id)?;
+    let before_id = existing_node(&gui, before_id)?;
+
+    gui.insert_before(parent_id, child_id, before_id);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_remove_child_from_container(
+    state: &mut OpState,
+    #[bigint] node_id: usize,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let node_id = existing_node(&gui, node_id)?;
+
+    gui.remove_child_from_root(node_id);
+
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_remove_child(
+    state: &mut OpState,
+    #[bigint] parent_id: usize,
+    #[bigint] child_id: usize,
+) -> Result<(), JsErrorBox> {
+    let mut gui = state.borrow::<Arc<Mutex<Gui>>>().lock().unwrap();
+    let parent_id = existing_node(&gui, parent_id)?;
+    let child_id = existing_node(&gui, child_id)?;
+
+    gui.remove_child(parent_id, child_id);
 
     Ok(())
 }
@@ -81,69 +612,171 @@ extension!(
     rect_extension,
     ops = [
         op_create_instance,
+        op_update_instance,
+        op_set_text,
+        op_reset_text,
+        op_set_image,
+        op_reset_image,
+        op_set_box_shadows,
+        op_set_background_gradients,
+        op_set_transform,
+        op_set_opacity,
+        op_set_z_index,
+        op_scroll_to,
+        op_set_overlay_scrollbar_width,
+        op_request_animation_frame,
+        op_get_pixel_ratio,
+        op_open_window,
+        op_set_window_options,
+        op_set_main_window_options,
+        op_close_window,
+        op_start_animation,
+        op_stop_animation,
+        op_set_transitions,
+        op_set_focusable,
         op_append_child_to_container,
         op_append_child,
+        op_insert_in_container_before,
+        op_insert_before,
+        op_remove_child_from_container,
+        op_remove_child,
     ],
 );
 
-pub fn run_script(gui: Arc<Mutex<Gui>>, js_path: &str) {
-    let js_path_buf = Path::new(env!("CARGO_MANIFEST_DIR")).join(js_path);
+/// Parses a css color, falling back to `default` for invalid ones
+fn parse_color_or(color: &str, default: &str) -> DynamicColor {
+    parse_color(color).unwrap_or(DynamicColor::from_str(default).unwrap())
+}
 
+pub enum ScriptEvent {
+    FileChanged(notify::Result<notify::Event>),
+    Dispatch(JsEvent),
+    /// A frame was drawn, with its time in milliseconds since the app started
+    AnimationFrame(f64),
+    /// An animation started with `op_start_animation` ended, and whether it
+    /// ran to the end
+    AnimationEnd(u32, bool),
+}
+
+pub fn run_script(
+    gui: Arc<Mutex<Gui>>,
+    events: EventSender,
+    tx: Sender<ScriptEvent>,
+    rx: Receiver<ScriptEvent>,
+    js_path: PathBuf,
+    watch: bool,
+) {
     let _handle = thread::spawn(move || {
-        let (tx, rx) = mpsc::channel();
-
-        let mut watcher = recommended_watcher(tx).unwrap();
-        watcher
-            .watch(
-                Path::new(env!("CARGO_MANIFEST_DIR")).join("src").as_ref(),
-                RecursiveMode::NonRecursive,
-            )
-            .unwrap();
-
-        let mut runtime = match init_runtime(gui.clone()) {
-            Ok(runtime) => runtime,
-            Err(error) => {
-                eprintln!("{error}");
-                return;
-            }
-        };
+        let script_dir = js_path.parent().unwrap_or(Path::new(".")).to_path_buf();
 
-        let module = match Module::load(&js_path_buf) {
-            Ok(module) => module,
-            Err(error) => {
-                eprintln!("{error}");
-                return;
+        // the script keeps running without reloading when watching fails
+        let watcher = if watch {
+            match ModuleWatcher::new(tx) {
+                Ok(mut watcher) => {
+                    // the entry is not imported through the module loader
+                    watcher.watch(&js_path);
+                    Some(Arc::new(Mutex::new(watcher)))
+                }
+                Err(error) => {
+                    eprintln!("Failed to watch the script: {error}");
+                    None
+                }
             }
+        } else {
+            None
         };
 
-        if let Err(error) = runtime.load_module(&module) {
-            eprintln!("{error}");
-        }
+        let mut runtime = start_runtime(gui.clone(), &script_dir, watcher.clone());
+        let loaded = runtime
+            .as_mut()
+            .is_some_and(|runtime| load_script(runtime, &js_path));
+
+        // the app quits when it fails without watching
+        let _ = events.send_event(CustomEvent::ScriptLoaded(loaded));
+
+        // set by changes to the module graph, pushed back by each change
+        // that follows shortly
+        let mut reload_at: Option<Instant> = None;
 
         loop {
-            match rx.recv() {
-                Ok(event) => {
-                    if let Ok(event) = event {
-                        if let EventKind::Modify(ModifyKind::Data(_)) = event.kind {
-                            println!("reloading... ");
-                            let module = match Module::load(&js_path_buf) {
-                                Ok(module) => module,
-                                Err(error) => {
-                                    eprintln!("{error}");
-                                    return;
-                                }
-                            };
-
-                            gui.lock().unwrap().clear();
-
-                            if let Err(error) = runtime.load_module(&module) {
-                                eprintln!("{error}");
-                            }
-                        } else {
-                            continue;
+            let event = match reload_at {
+                Some(reload_at) => {
+                    rx.recv_timeout(reload_at.saturating_duration_since(Instant::now()))
+                }
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match event {
+                Ok(ScriptEvent::FileChanged(Ok(event))) => {
+                    let is_change = watcher
+                        .as_ref()
+                        .and_then(|watcher| watcher.lock().ok())
+                        .is_some_and(|watcher| watcher.is_change(&event));
+
+                    if is_change {
+                        reload_at = Some(Instant::now() + RELOAD_DEBOUNCE);
+                    }
+                }
+                Ok(ScriptEvent::FileChanged(Err(error))) => {
+                    eprintln!("{error}");
+                }
+                Ok(ScriptEvent::Dispatch(event)) => {
+                    if let Some(runtime) = &mut runtime {
+                        if let Err(error) =
+                            runtime.call_function::<Value>(None, "__dispatchGuiEvent", &[event])
+                        {
+                            eprintln!("{error}");
                         }
-                    } else {
-                        continue;
+                    }
+                }
+                Ok(ScriptEvent::AnimationFrame(timestamp)) => {
+                    if let Some(runtime) = &mut runtime {
+                        if let Err(error) = runtime.call_function::<Value>(
+                            None,
+                            "__runAnimationFrame",
+                            &[timestamp],
+                        ) {
+                            eprintln!("{error}");
+                        }
+                    }
+                }
+                Ok(ScriptEvent::AnimationEnd(id, finished)) => {
+                    if let Some(runtime) = &mut runtime {
+                        if let Err(error) = runtime.call_function::<Value>(
+                            None,
+                            "__finishAnimation",
+                            &(id, finished),
+                        ) {
+                            eprintln!("{error}");
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    reload_at = None;
+
+                    println!("reloading... ");
+
+                    // nothing of the old script may run on in the new one
+                    if let Some(runtime) = runtime.take() {
+                        dispose_runtime(runtime);
+                    }
+
+                    if let Ok(mut gui) = gui.lock() {
+                        gui.clear();
+                    }
+
+                    // the new runtime imports the graph again, which may
+                    // have changed
+                    if let Some(watcher) = &watcher {
+                        if let Ok(mut watcher) = watcher.lock() {
+                            watcher.clear();
+                            watcher.watch(&js_path);
+                        }
+                    }
+
+                    runtime = start_runtime(gui.clone(), &script_dir, watcher.clone());
+                    if let Some(runtime) = &mut runtime {
+                        load_script(runtime, &js_path);
                     }
                 }
                 Err(error) => {
@@ -155,16 +788,88 @@ pub fn run_script(gui: Arc<Mutex<Gui>>, js_path: &str) {
     });
 }
 
-fn init_runtime(gui: Arc<Mutex<Gui>>) -> Result<Runtime, Error> {
+/// A new runtime for the script, None when it failed to start
+fn start_runtime(
+    gui: Arc<Mutex<Gui>>,
+    script_dir: &Path,
+    watcher: Option<Arc<Mutex<ModuleWatcher>>>,
+) -> Option<Runtime> {
+    init_runtime(gui, script_dir.to_path_buf(), watcher)
+        .inspect_err(|error| eprintln!("{error}"))
+        .ok()
+}
+
+/// Runs the entry script, returns whether it loaded without throwing
+fn load_script(runtime: &mut Runtime, js_path: &Path) -> bool {
+    let module = match Module::load(js_path) {
+        Ok(module) => module,
+        Err(error) => {
+            eprintln!("{error}");
+            return false;
+        }
+    };
+
+    runtime
+        .load_module(&module)
+        .inspect_err(|error| eprintln!("{error}"))
+        .is_ok()
+}
+
+/// Unmounts what the script rendered so its effects clean up, then drops
+/// the runtime and with it its timers and cached modules
+fn dispose_runtime(mut runtime: Runtime) {
+    match runtime.call_function::<Value>(None, "__dispose", json_args!()) {
+        // the script failed before it imported ReactWGPU
+        Ok(_) | Err(Error::ValueNotFound(_)) => (),
+        Err(error) => eprintln!("{error}"),
+    }
+}
+
+/*
+ * Adds the modules the script imports to the watched module graph, and
+ * leaves loading them to rustyscript
+ */
+struct WatchImports(Arc<Mutex<ModuleWatcher>>);
+
+impl ImportProvider for WatchImports {
+    fn import(
+        &mut self,
+        specifier: &ModuleSpecifier,
+        _referrer: Option<&ModuleSpecifier>,
+        _is_dyn_import: bool,
+        _requested_module_type: RequestedModuleType,
+    ) -> Option<Result<String, ModuleLoaderError>> {
+        if let Ok(path) = specifier.to_file_path() {
+            if let Ok(mut watcher) = self.0.lock() {
+                watcher.watch(&path);
+            }
+        }
+
+        None
+    }
+}
+
+fn init_runtime(
+    gui: Arc<Mutex<Gui>>,
+    script_dir: PathBuf,
+    watcher: Option<Arc<Mutex<ModuleWatcher>>>,
+) -> Result<Runtime, Error> {
     println!("Initializing runtime...");
     let mut runtime = Runtime::new(RuntimeOptions {
         extensions: vec![rect_extension::init_ops_and_esm()],
+        import_provider: watcher
+            .map(|watcher| Box::new(WatchImports(watcher)) as Box<dyn ImportProvider>),
         ..RuntimeOptions::default()
     })?;
 
     runtime.deno_runtime().op_state().borrow_mut().put(gui);
+    runtime
+        .deno_runtime()
+        .op_state()
+        .borrow_mut()
+        .put(ScriptDir(script_dir.clone()));
 
-    runtime.set_current_dir("src")?;
+    runtime.set_current_dir(script_dir)?;
 
     println!("Runtime initialized successfully.");
 
@@ -0,0 +1,83 @@
+use notify::event::ModifyKind;
+use notify::{recommended_watcher, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use super::ScriptEvent;
+
+/*
+ * Watches the files in the module graph of the script, which grows as the
+ * script imports more of them. Editors often save by writing another file
+ * and renaming it over the module, which ends a watch on the file itself,
+ * so their directories are watched instead and the events filtered.
+ */
+pub struct ModuleWatcher {
+    watcher: RecommendedWatcher,
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl ModuleWatcher {
+    pub fn new(tx: Sender<ScriptEvent>) -> notify::Result<Self> {
+        let watcher = recommended_watcher(move |event| {
+            let _ = tx.send(ScriptEvent::FileChanged(event));
+        })?;
+
+        Ok(Self {
+            watcher,
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    /// Adds a module to the graph, packages in node_modules are left out
+    pub fn watch(&mut self, path: &Path) {
+        if path
+            .components()
+            .any(|component| component.as_os_str() == "node_modules")
+        {
+            return;
+        }
+
+        // events name files by their real path on some platforms
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        let Some(dir) = path.parent().map(Path::to_path_buf) else {
+            return;
+        };
+
+        if !self.files.insert(path) || self.dirs.contains(&dir) {
+            return;
+        }
+
+        match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                self.dirs.insert(dir);
+            }
+            Err(error) => eprintln!("Failed to watch {}: {error}", dir.display()),
+        }
+    }
+
+    /// Forgets the graph, for a runtime that imports it anew
+    pub fn clear(&mut self) {
+        for dir in self.dirs.drain() {
+            let _ = self.watcher.unwatch(&dir);
+        }
+        self.files.clear();
+    }
+
+    /// Whether the event may have changed a module in the graph
+    pub fn is_change(&self, event: &notify::Event) -> bool {
+        let may_change = match event.kind {
+            EventKind::Modify(ModifyKind::Metadata(_)) => false,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => true,
+            // some platforms cannot tell what happened
+            EventKind::Any => true,
+            EventKind::Access(_) | EventKind::Other => false,
+        };
+
+        may_change && event.paths.iter().any(|path| self.files.contains(path))
+    }
+}
@@ -0,0 +1,28 @@
+// @ts-expect-error not typed yet
+const get_pixel_ratio = Deno.core.ops.op_get_pixel_ratio;
+
+/**
+ * Density of the display the window is on, like `PixelRatio` of react
+ * native. Styles are in logical pixels, each covering `get()` physical ones.
+ */
+export const PixelRatio = {
+  // physical pixels per logical pixel, e.g. 2 on most retina displays
+  get(): number {
+    return get_pixel_ratio();
+  },
+
+  // text follows the same density, there is no separate font scale yet
+  getFontScale(): number {
+    return 1;
+  },
+
+  getPixelSizeForLayoutSize(layoutSize: number): number {
+    return Math.round(layoutSize * PixelRatio.get());
+  },
+
+  // the logical size closest to a whole number of physical pixels, for lines that stay sharp
+  roundToNearestPixel(layoutSize: number): number {
+    const ratio = PixelRatio.get();
+    return Math.round(layoutSize * ratio) / ratio;
+  },
+};
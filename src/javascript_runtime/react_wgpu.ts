@@ -1,11 +1,25 @@
 import { reconciler } from "./reconciler.ts";
+import "./animation_frame.ts";
+import { setMainWindowOptions } from "./window.ts";
+import type { WindowOptionProps } from "./window.ts";
 import type { ReactNode } from "react";
 
+export { PixelRatio } from "./pixel_ratio.ts";
+export { Window } from "./window.ts";
+
 // from react-reconciler/constants, which cannot be imported with rustyscript
 const ConcurrentRoot = 1;
 
+// unmounted before the script reloads, see `dispose`
+const containers: ReturnType<typeof reconciler.createContainer>[] = [];
+
 export const ReactWGPU = {
-  render(rootInstance: ReactNode) {
+  // the options set the title, size and so on of the main window
+  render(rootInstance: ReactNode, windowOptions?: WindowOptionProps) {
+    if (windowOptions !== undefined) {
+      setMainWindowOptions(windowOptions);
+    }
+
     const container = reconciler.createContainer(
       { type: "container" },
       ConcurrentRoot,
@@ -17,5 +31,18 @@ export const ReactWGPU = {
       null
     );
     reconciler.updateContainer(rootInstance, container, null, null);
+    containers.push(container);
   },
 };
+
+// called from rust before the runtime is dropped for a reload, so that
+// effects clean up and windows close while their nodes still exist
+function dispose() {
+  for (const container of containers.splice(0)) {
+    reconciler.updateContainerSync(null, container, null, null);
+  }
+  reconciler.flushSyncWork();
+}
+
+// @ts-expect-error not typed yet
+globalThis.__dispose = dispose;
@@ -1,13 +1,69 @@
 import ReactReconciler from "react-reconciler";
-import { taffyFromCss } from "./taffy.ts";
+import {
+  borderRadiusFromCss,
+  gradientsFromCss,
+  overlayScrollbarWidthFromCss,
+  splitTopLevel,
+  taffyFromCss,
+  transformFromCss,
+} from "./taffy.ts";
+import type { BorderRadius, Style, Transform } from "./taffy.ts";
+import { dispatchScroll, setListeners, removeListeners, takeClosedWindow } from "./events.ts";
+import type { GuiCloseEvent, GuiFocusEvent, GuiKeyboardEvent, GuiPointerEvent, GuiScrollEvent } from "./events.ts";
+import { animate, transitionsFromCss } from "./animation.ts";
+import type { AnimatedStyle, AnimationHandle, AnimationOptions } from "./animation.ts";
+import { changedWindowOptions, toWindowOptions } from "./window.ts";
+import type { WindowOptionProps } from "./window.ts";
 
 // @ts-expect-error not typed yet
 export const create_instance = Deno.core.ops.op_create_instance;
 // @ts-expect-error not typed yet
+export const update_instance = Deno.core.ops.op_update_instance;
+// @ts-expect-error not typed yet
+export const set_text = Deno.core.ops.op_set_text;
+// @ts-expect-error not typed yet
+export const reset_text = Deno.core.ops.op_reset_text;
+// @ts-expect-error not typed yet
+export const set_image = Deno.core.ops.op_set_image;
+// @ts-expect-error not typed yet
+export const reset_image = Deno.core.ops.op_reset_image;
+// @ts-expect-error not typed yet
+export const set_box_shadows = Deno.core.ops.op_set_box_shadows;
+// @ts-expect-error not typed yet
+export const set_background_gradients = Deno.core.ops.op_set_background_gradients;
+// @ts-expect-error not typed yet
+export const set_transform = Deno.core.ops.op_set_transform;
+// @ts-expect-error not typed yet
+export const set_opacity = Deno.core.ops.op_set_opacity;
+// @ts-expect-error not typed yet
+export const set_z_index = Deno.core.ops.op_set_z_index;
+// @ts-expect-error not typed yet
+export const scroll_to = Deno.core.ops.op_scroll_to;
+// @ts-expect-error not typed yet
+export const set_overlay_scrollbar_width = Deno.core.ops.op_set_overlay_scrollbar_width;
+// @ts-expect-error not typed yet
+export const set_transitions = Deno.core.ops.op_set_transitions;
+// @ts-expect-error not typed yet
+export const set_focusable = Deno.core.ops.op_set_focusable;
+// @ts-expect-error not typed yet
+export const open_window = Deno.core.ops.op_open_window;
+// @ts-expect-error not typed yet
+export const set_window_options = Deno.core.ops.op_set_window_options;
+// @ts-expect-error not typed yet
+export const close_window = Deno.core.ops.op_close_window;
+// @ts-expect-error not typed yet
 export const append_child_to_container = Deno.core.ops.op_append_child_to_container;
 // @ts-expect-error not typed yet
 export const append_child = Deno.core.ops.op_append_child;
 // @ts-expect-error not typed yet
+export const insert_in_container_before = Deno.core.ops.op_insert_in_container_before;
+// @ts-expect-error not typed yet
+export const insert_before = Deno.core.ops.op_insert_before;
+// @ts-expect-error not typed yet
+export const remove_child_from_container = Deno.core.ops.op_remove_child_from_container;
+// @ts-expect-error not typed yet
+export const remove_child = Deno.core.ops.op_remove_child;
+// @ts-expect-error not typed yet
 export const get_style_defaults = Deno.core.ops.op_get_style_defaults;
 // @ts-expect-error not typed yet
 export const debug = Deno.core.ops.op_debug;
@@ -15,16 +71,39 @@ export const debug = Deno.core.ops.op_debug;
 import type { CSSProperties, ReactNode } from "react";
 
 type RectId = number;
-type RectProps = { style: CSSProperties };
+type RectProps = WindowOptionProps & {
+  style: CSSProperties;
+  children?: ReactNode;
+  src?: string;
+  onClick?: (event: GuiPointerEvent) => void;
+  onPointerDown?: (event: GuiPointerEvent) => void;
+  onPointerUp?: (event: GuiPointerEvent) => void;
+  onPointerMove?: (event: GuiPointerEvent) => void;
+  onKeyDown?: (event: GuiKeyboardEvent) => void;
+  onKeyUp?: (event: GuiKeyboardEvent) => void;
+  onFocus?: (event: GuiFocusEvent) => void;
+  onBlur?: (event: GuiFocusEvent) => void;
+  onScroll?: (event: GuiScrollEvent) => void;
+  onClose?: (event: GuiCloseEvent) => void;
+  tabIndex?: number;
+  focusable?: boolean;
+};
 
 type Type = Pick<Container | Instance | TextInstance | HostContext, "type">;
 type Props = RectProps;
 type Container = { type: "container" };
-type Instance = { type: "div"; id: RectId };
-type TextInstance = { type: "text" };
+// text instances take the text style of the instance they are in, like in css
+type Instance = { type: string; id: RectId; style: CSSProperties; texts: Set<TextInstance> };
+type TextInstance = { type: "text"; id: RectId; text: string; parent: Instance | null };
 type SuspenseInstance = never;
 type HydratableInstance = never;
-type PublicInstance = { type: string };
+type ScrollToOptions = { left?: number; top?: number };
+type PublicInstance = {
+  type: string;
+  scrollTo(options: ScrollToOptions): void;
+  scrollTo(x: number, y: number): void;
+  animate(style: AnimatedStyle, options?: AnimationOptions, onFinish?: (finished: boolean) => void): AnimationHandle;
+};
 type HostContext = { type: "context" };
 type UpdatePayload = RectProps;
 type ChildSet = never;
@@ -58,53 +137,163 @@ export const reconciler = ReactReconciler<
   supportsPersistence: false,
   noTimeout: -1,
 
-  createInstance(_type, props, _rootContainerInstance, _hostContext, _internalInstanceHandle) {
-    const taffyStyle = taffyFromCss(props.style as Record<string, unknown>);
-    const { backgroundColor = "transparent", borderRadius = 0 } = props.style;
-    const id = create_instance(taffyStyle, backgroundColor, borderRadius);
-    return { type: "div", id };
+  createInstance(type, props, _rootContainerInstance, _hostContext, _internalInstanceHandle) {
+    const id = isWindow(type) ? openWindow(props) : create_instance(...toInstanceArgs(props));
+    if (hasTransitions(props)) {
+      set_transitions(id, transitionsFromCss(props.style));
+    }
+    if (isTextContent(props.children)) {
+      set_text(id, ...toTextArgs(String(props.children), props.style));
+    }
+    if (isImage(type) && props.src !== undefined) {
+      set_image(id, ...toImageArgs(props.src, props.style));
+    }
+    if (props.style.boxShadow !== undefined) {
+      set_box_shadows(id, toBoxShadows(props.style.boxShadow));
+    }
+    if (props.style.backgroundImage !== undefined) {
+      set_background_gradients(id, gradientsFromCss(props.style));
+    }
+    if (props.style.transform !== undefined) {
+      set_transform(id, ...toTransformArgs(props.style));
+    }
+    if (props.style.opacity !== undefined) {
+      set_opacity(id, toOpacity(props.style.opacity));
+    }
+    if (props.style.zIndex !== undefined) {
+      set_z_index(id, toZIndex(props.style.zIndex));
+    }
+    set_overlay_scrollbar_width(id, overlayScrollbarWidthFromCss(props.style));
+    setListeners(id, props);
+    set_focusable(id, isFocusable(props));
+    return { type: String(type), id, style: props.style, texts: new Set() };
+  },
+
+  commitUpdate(instance, type, oldProps, newProps) {
+    // like css, the new transitions already apply to this update
+    if (hasTransitionChanged(oldProps, newProps)) {
+      set_transitions(instance.id, transitionsFromCss(newProps.style ?? {}));
+    }
+    if (isWindow(type)) {
+      updateWindow(instance.id, oldProps, newProps);
+    } else {
+      update_instance(instance.id, ...toInstanceArgs(newProps));
+    }
+    if (isImage(type) && hasImageChanged(oldProps, newProps)) {
+      if (newProps.src === undefined) {
+        reset_image(instance.id);
+      } else {
+        set_image(instance.id, ...toImageArgs(newProps.src, newProps.style));
+      }
+    }
+    if (oldProps.style?.boxShadow !== newProps.style?.boxShadow) {
+      set_box_shadows(instance.id, toBoxShadows(newProps.style?.boxShadow));
+    }
+    if (oldProps.style?.backgroundImage !== newProps.style?.backgroundImage) {
+      set_background_gradients(instance.id, gradientsFromCss(newProps.style ?? {}));
+    }
+    if (hasTransformChanged(oldProps, newProps)) {
+      set_transform(instance.id, ...toTransformArgs(newProps.style));
+    }
+    if (oldProps.style?.opacity !== newProps.style?.opacity) {
+      set_opacity(instance.id, toOpacity(newProps.style?.opacity));
+    }
+    if (oldProps.style?.zIndex !== newProps.style?.zIndex) {
+      set_z_index(instance.id, toZIndex(newProps.style?.zIndex));
+    }
+    set_overlay_scrollbar_width(instance.id, overlayScrollbarWidthFromCss(newProps.style ?? {}));
+    setListeners(instance.id, newProps);
+    set_focusable(instance.id, isFocusable(newProps));
+    if (isTextContent(newProps.children)) {
+      set_text(instance.id, ...toTextArgs(String(newProps.children), newProps.style));
+    }
+    instance.style = newProps.style ?? {};
+    if (hasTextStyleChanged(oldProps, newProps)) {
+      instance.texts.forEach(updateText);
+    }
+  },
+
+  resetTextContent(instance) {
+    reset_text(instance.id);
   },
 
+  // windows are roots of their own and never children of their parent
+
   appendChildToContainer(_container, child) {
-    if (child.type === "div") {
+    if (!isWindow(child.type)) {
       append_child_to_container(child.id);
-    } else {
-      console.warn("appendChildToContainer: Ignoring child", child);
     }
   },
 
   appendInitialChild(parent, child) {
-    if (child.type === "div") {
+    if (!isWindow(child.type)) {
       append_child(parent.id, child.id);
-    } else {
-      console.warn("appendInitialChild: Ignoring child", child);
+      attachText(parent, child);
     }
   },
 
   appendChild(parent, child) {
-    if (child.type === "div") {
+    if (!isWindow(child.type)) {
       append_child(parent.id, child.id);
-    } else {
-      console.warn("appendChild: Ignoring child", child);
+      attachText(parent, child);
     }
   },
 
-  createTextInstance(_text, _rootContainerInstance, _hostContext, _internalInstanceHandle) {
-    return { type: "text" };
+  insertInContainerBefore(_container, child, beforeChild) {
+    if (!isWindow(child.type)) {
+      insert_in_container_before(child.id, beforeChild.id);
+    }
+  },
+
+  insertBefore(parent, child, beforeChild) {
+    if (!isWindow(child.type)) {
+      insert_before(parent.id, child.id, beforeChild.id);
+      attachText(parent, child);
+    }
+  },
+
+  removeChildFromContainer(_container, child) {
+    if (!isWindow(child.type)) {
+      remove_child_from_container(child.id);
+    }
+  },
+
+  removeChild(parent, child) {
+    if (!isWindow(child.type)) {
+      remove_child(parent.id, child.id);
+      detachText(parent, child);
+    }
+  },
+
+  createTextInstance(text, _rootContainerInstance, _hostContext, _internalInstanceHandle) {
+    const id = create_instance(...toInstanceArgs({ style: {} }));
+    set_text(id, ...toTextArgs(text));
+    return { type: "text", id, text, parent: null };
+  },
+
+  commitTextUpdate(textInstance, _oldText, newText) {
+    textInstance.text = newText;
+    updateText(textInstance);
   },
 
   clearContainer: () => false,
   prepareForCommit: () => null,
   preparePortalMount: () => {},
   resetAfterCommit: () => {},
-  shouldSetTextContent: () => false,
+  shouldSetTextContent: (_type, props) => isTextContent(props.children),
   hideInstance() {},
   unhideInstance() {},
   hideTextInstance: () => {},
   unhideTextInstance: () => {},
   beforeActiveInstanceBlur: () => {},
   afterActiveInstanceBlur: () => {},
-  detachDeletedInstance: () => {},
+  // called for every instance of a removed subtree, so nested windows close too
+  detachDeletedInstance(instance) {
+    removeListeners(instance.id);
+    if (isWindow(instance.type) && !takeClosedWindow(instance.id)) {
+      close_window(instance.id);
+    }
+  },
   // @ts-expect-error badly typed by react-reconciler
   scheduleTimeout: setTimeout,
   cancelTimeout: clearTimeout,
@@ -116,7 +305,7 @@ export const reconciler = ReactReconciler<
   prepareUpdate: (_i, _t, _o, newProps) => newProps,
   getRootHostContext: () => ({ type: "context" }),
   getChildHostContext: () => ({ type: "context" }),
-  getPublicInstance: ({ type }) => ({ type }),
+  getPublicInstance: toPublicInstance,
   getCurrentEventPriority: () => NoEventPriority,
   setCurrentUpdatePriority: newPriority => {
     currentUpdatePriority = newPriority;
@@ -128,3 +317,203 @@ export const reconciler = ReactReconciler<
     return currentUpdatePriority || DefaultEventPriority;
   },
 });
+
+type BorderColors = [string, string, string, string];
+
+function toInstanceArgs(props: Props): [Partial<Style>, string, BorderRadius, BorderColors, string] {
+  const css = props.style as Record<string, unknown>;
+  const taffyStyle = taffyFromCss(css);
+  const { backgroundColor = "transparent", borderStyle = "solid" } = props.style;
+  return [taffyStyle, backgroundColor, borderRadiusFromCss(css), toBorderColors(props.style), borderStyle];
+}
+
+// top, right, bottom, left like the border widths, css defaults to the text color
+function toBorderColors(style: CSSProperties): BorderColors {
+  const { color = "black" } = style;
+  const { borderColor = color } = style;
+  const {
+    borderTopColor = borderColor,
+    borderRightColor = borderColor,
+    borderBottomColor = borderColor,
+    borderLeftColor = borderColor,
+  } = style;
+  return [borderTopColor, borderRightColor, borderBottomColor, borderLeftColor];
+}
+
+function toTextArgs(text: string, style: CSSProperties = {}): [string, string, number, number] {
+  const { color = "black", fontSize = 16, lineHeight = 1.2 } = style;
+  const fontSizePx = toPixels(fontSize);
+  // unitless line heights are multiples of the font size, like in css
+  const lineHeightPx = typeof lineHeight === "number" ? lineHeight * fontSizePx : toPixels(lineHeight);
+  return [text, color, fontSizePx, lineHeightPx];
+}
+
+const textStyleKeys = ["color", "fontSize", "lineHeight"] as const;
+
+function hasTextStyleChanged(oldProps: Props, newProps: Props): boolean {
+  return textStyleKeys.some(key => oldProps.style?.[key] !== newProps.style?.[key]);
+}
+
+function isTextInstance(instance: Instance | TextInstance): instance is TextInstance {
+  return "parent" in instance;
+}
+
+function attachText(parent: Instance, child: Instance | TextInstance) {
+  if (isTextInstance(child)) {
+    child.parent = parent;
+    parent.texts.add(child);
+    updateText(child);
+  }
+}
+
+function detachText(parent: Instance, child: Instance | TextInstance) {
+  if (isTextInstance(child)) {
+    child.parent = null;
+    parent.texts.delete(child);
+  }
+}
+
+function updateText(textInstance: TextInstance) {
+  set_text(textInstance.id, ...toTextArgs(textInstance.text, textInstance.parent?.style));
+}
+
+type BoxShadow = {
+  offset_x: number;
+  offset_y: number;
+  blur: number;
+  spread: number;
+  color: string;
+  inset: boolean;
+};
+
+// "inset 0 1px 2px rgba(0, 0, 0, 0.2), 0 0 0 1px red"
+function toBoxShadows(boxShadow: string | undefined): BoxShadow[] {
+  if (boxShadow === undefined || boxShadow === "none") {
+    return [];
+  }
+
+  return splitTopLevel(boxShadow, ",").map(shadow => {
+    const tokens = splitTopLevel(shadow.trim(), " ").filter(token => token !== "");
+    const lengths = tokens.filter(isLength).map(toPixels);
+    const colorTokens = tokens.filter(token => token !== "inset" && !isLength(token));
+    const [offset_x = 0, offset_y = 0, blur = 0, spread = 0] = lengths;
+
+    return {
+      offset_x,
+      offset_y,
+      blur,
+      spread,
+      color: colorTokens.join(" ") || "black",
+      inset: tokens.includes("inset"),
+    };
+  });
+}
+
+function isLength(token: string): boolean {
+  return /^-?[\d.]+(px)?$/.test(token);
+}
+
+function toTransformArgs(style: CSSProperties = {}): [Transform["functions"], Transform["origin"]] {
+  const { functions, origin } = transformFromCss(style as Record<string, unknown>);
+  return [functions, origin];
+}
+
+function hasTransformChanged(oldProps: Props, newProps: Props): boolean {
+  return (
+    oldProps.style?.transform !== newProps.style?.transform ||
+    oldProps.style?.transformOrigin !== newProps.style?.transformOrigin
+  );
+}
+
+const transitionKeys = [
+  "transition",
+  "transitionProperty",
+  "transitionDuration",
+  "transitionTimingFunction",
+  "transitionDelay",
+] as const;
+
+function hasTransitions({ style }: Props): boolean {
+  return transitionKeys.some(key => style[key] !== undefined);
+}
+
+function hasTransitionChanged(oldProps: Props, newProps: Props): boolean {
+  return transitionKeys.some(key => oldProps.style?.[key] !== newProps.style?.[key]);
+}
+
+function toOpacity(opacity: CSSProperties["opacity"] = 1): number {
+  // "50%" is valid css too
+  const value = typeof opacity === "string" && opacity.endsWith("%") ? parseFloat(opacity) / 100 : Number(opacity);
+  return Number.isNaN(value) ? 1 : value;
+}
+
+// null is `auto`, which paints along with the parent
+function toZIndex(zIndex: CSSProperties["zIndex"]): number | null {
+  const value = Number(zIndex);
+  return zIndex === undefined || zIndex === "auto" || !Number.isInteger(value) ? null : value;
+}
+
+// what refs get, with the scrolling methods of dom elements
+function toPublicInstance({ type, id }: Instance | TextInstance): PublicInstance {
+  return {
+    type,
+    scrollTo(optionsOrX: ScrollToOptions | number, y?: number) {
+      const { left, top } = typeof optionsOrX === "number" ? { left: optionsOrX, top: y } : optionsOrX;
+      const offset = scroll_to(id, left, top);
+      if (offset !== null) {
+        dispatchScroll(id, offset);
+      }
+    },
+    animate(style: AnimatedStyle, options?: AnimationOptions, onFinish?: (finished: boolean) => void) {
+      return animate(id, style, options, onFinish);
+    },
+  };
+}
+
+function toImageArgs(src: string, style: CSSProperties = {}): [string, string] {
+  const { objectFit = "fill" } = style;
+  return [src, objectFit];
+}
+
+function isImage(type: unknown): boolean {
+  return type === "img" || type === "image";
+}
+
+function isWindow(type: unknown): boolean {
+  return type === "window";
+}
+
+function openWindow(props: Props): RectId {
+  const id = open_window(toWindowOptions(props));
+  update_instance(id, ...toInstanceArgs(toWindowProps(props)));
+  return id;
+}
+
+function updateWindow(id: RectId, oldProps: Props, newProps: Props) {
+  const options = changedWindowOptions(oldProps, newProps);
+  if (options !== null) {
+    set_window_options(id, options);
+  }
+  update_instance(id, ...toInstanceArgs(toWindowProps(newProps)));
+}
+
+// the root fills the window unless the style sizes it
+function toWindowProps(props: Props): Props {
+  return { ...props, style: { width: "100%", height: "100%", ...props.style } };
+}
+
+function hasImageChanged(oldProps: Props, newProps: Props): boolean {
+  return oldProps.src !== newProps.src || oldProps.style?.objectFit !== newProps.style?.objectFit;
+}
+
+function isFocusable({ focusable, tabIndex }: Props): boolean {
+  return focusable ?? (tabIndex !== undefined && tabIndex >= 0);
+}
+
+function isTextContent(children: ReactNode): children is string | number {
+  return typeof children === "string" || typeof children === "number";
+}
+
+function toPixels(value: string | number): number {
+  return typeof value === "number" ? value : parseFloat(value);
+}
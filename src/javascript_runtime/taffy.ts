@@ -196,6 +196,15 @@ export function taffyFromCss<T extends Record<string, unknown>>(css: T): Partial
       .with("overflow", () => {
         taffy.overflow = pipe(value, isString, toShorthand2, map2(toOverflow), toPoint);
       })
+      .with("overflowX", () => {
+        taffy.overflow = { x: pipe(value, isString, toOverflow), y: taffy.overflow?.y ?? "Visible" };
+      })
+      .with("overflowY", () => {
+        taffy.overflow = { x: taffy.overflow?.x ?? "Visible", y: pipe(value, isString, toOverflow) };
+      })
+      .with("scrollbarWidth", () => {
+        taffy.scrollbar_width = pipe(value, isStringOrNum, toScrollbarWidth);
+      })
       .with("position", () => {
         taffy.position = pipe(value, isString, toPosition);
       })
@@ -229,9 +238,21 @@ export function taffyFromCss<T extends Record<string, unknown>>(css: T): Partial
       .with("padding", () => {
         taffy.padding = pipe(value, isStringOrNum, toShorthand4, map4(toLengthPercentage), toRect);
       })
-      .with("border", () => {
+      .with(P.union("border", "borderWidth"), () => {
         taffy.border = pipe(value, isStringOrNum, toShorthand4, map4(toLengthPercentage), toRect);
       })
+      .with("borderTopWidth", () => {
+        taffy.border = { ...(taffy.border ?? zeroRect()), top: pipe(value, isStringOrNum, toLengthPercentage) };
+      })
+      .with("borderRightWidth", () => {
+        taffy.border = { ...(taffy.border ?? zeroRect()), right: pipe(value, isStringOrNum, toLengthPercentage) };
+      })
+      .with("borderBottomWidth", () => {
+        taffy.border = { ...(taffy.border ?? zeroRect()), bottom: pipe(value, isStringOrNum, toLengthPercentage) };
+      })
+      .with("borderLeftWidth", () => {
+        taffy.border = { ...(taffy.border ?? zeroRect()), left: pipe(value, isStringOrNum, toLengthPercentage) };
+      })
       .with("alignItems", () => {
         taffy.align_items = pipe(value, isString, toAlignItems);
       })
@@ -276,9 +297,174 @@ export function taffyFromCss<T extends Record<string, unknown>>(css: T): Partial
       });
   }
 
+  // `scroll` reserves a gutter for the scrollbars unless told otherwise, `auto` paints them over the content
+  const scrolls = taffy.overflow?.x === "Scroll" || taffy.overflow?.y === "Scroll";
+  if (overlayScrollbarWidthFromCss(css) > 0) {
+    taffy.scrollbar_width = 0;
+  } else if (scrolls && taffy.scrollbar_width === undefined) {
+    taffy.scrollbar_width = SCROLLBAR_WIDTH;
+  }
+
   return taffy;
 }
 
+const SCROLLBAR_WIDTH = 10;
+
+// not a taffy style, the width of the scrollbars `overflow: auto` shows over the content while it overflows,
+// 0 when the node has none or reserves a gutter because an axis is `scroll`
+export function overlayScrollbarWidthFromCss<T extends Record<string, unknown>>(css: T): number {
+  const { overflow = "visible", overflowX, overflowY, scrollbarWidth = "auto" } = css;
+  const [x, y] = pipe(overflow, isString, toShorthand2);
+  const keywords = [overflowX ?? x, overflowY ?? y];
+  const overlays = keywords.includes("auto") && !keywords.includes("scroll");
+  return overlays ? pipe(scrollbarWidth, isStringOrNum, toScrollbarWidth) : 0;
+}
+
+export interface BorderRadius {
+  top_left: Size<LengthPercentage>;
+  top_right: Size<LengthPercentage>;
+  bottom_right: Size<LengthPercentage>;
+  bottom_left: Size<LengthPercentage>;
+}
+
+// not a taffy style, but resolved against the layout the same way
+export function borderRadiusFromCss<T extends Record<string, unknown>>(css: T): BorderRadius {
+  const { borderRadius = 0 } = css;
+
+  // "10px 20px / 5px" sets the horizontal radii before the slash and the vertical ones after it
+  const radii = pipe(borderRadius, isStringOrNum);
+  const [horizontal, vertical = horizontal] =
+    typeof radii === "number" ? [radii] : radii.split("/").map(part => part.trim());
+  const widths = pipe(horizontal, toShorthand4, map4(toLengthPercentage));
+  const heights = pipe(vertical, toShorthand4, map4(toLengthPercentage));
+  const [top_left, top_right, bottom_right, bottom_left] = widths.map((width, index) => ({
+    width,
+    height: heights[index],
+  }));
+
+  return {
+    top_left: toCornerRadius(css.borderTopLeftRadius, top_left),
+    top_right: toCornerRadius(css.borderTopRightRadius, top_right),
+    bottom_right: toCornerRadius(css.borderBottomRightRadius, bottom_right),
+    bottom_left: toCornerRadius(css.borderBottomLeftRadius, bottom_left),
+  };
+}
+
+export type TransformFunction =
+  | { Translate: [LengthPercentage, LengthPercentage] }
+  | { Scale: [number, number] }
+  | { Rotate: number }
+  | { Skew: [number, number] }
+  | { Matrix: [number, number, number, number, number, number] };
+
+export interface Transform {
+  functions: TransformFunction[];
+  origin: Point<LengthPercentage>;
+}
+
+// not a taffy style either, the functions apply right to left like css
+export function transformFromCss<T extends Record<string, unknown>>(css: T): Transform {
+  const { transform = "none", transformOrigin = "50% 50%" } = css;
+  const value = pipe(transform, isString).trim();
+  const functions =
+    value === "none"
+      ? []
+      : [...value.matchAll(/([a-zA-Z0-9]+)\(([^)]*)\)/g)].map(([, name, args]) =>
+          toTransformFunction(name, args.split(",").map(arg => arg.trim()))
+        );
+
+  return { functions, origin: pipe(transformOrigin, isStringOrNum, toTransformOrigin) };
+}
+
+export type LinearDirection = { Angle: number } | { Corner: [number, number] };
+
+export type RadialSize =
+  | "ClosestSide"
+  | "FarthestSide"
+  | "ClosestCorner"
+  | "FarthestCorner"
+  | { Explicit: [LengthPercentage, LengthPercentage] };
+
+export interface RadialGradient {
+  shape: "Circle" | "Ellipse";
+  size: RadialSize;
+  position: Point<LengthPercentage>;
+}
+
+export interface ConicGradient {
+  from: number;
+  position: Point<LengthPercentage>;
+}
+
+export type GradientKind = { Linear: LinearDirection } | { Radial: RadialGradient } | { Conic: ConicGradient };
+
+export interface ColorStop {
+  color: string;
+  position: LengthPercentage | null;
+}
+
+export interface Gradient {
+  kind: GradientKind;
+  stops: ColorStop[];
+}
+
+// not a taffy style either, the first gradient is painted on top like css
+export function gradientsFromCss<T extends Record<string, unknown>>(css: T): Gradient[] {
+  const { backgroundImage = "none" } = css;
+  const value = pipe(backgroundImage, isString).trim();
+
+  if (value === "none") {
+    return [];
+  }
+
+  return splitTopLevel(value, ",").map(layer => {
+    const [, name, args] = layer.trim().match(/^([a-z-]+)\((.*)\)$/s) ?? unknownProp("backgroundImage", layer)();
+    const [first, ...rest] = splitTopLevel(args, ",").map(arg => arg.trim());
+    const tokens = splitTopLevel(first, " ").filter(token => token !== "");
+
+    // the first argument sets the shape, unless it is already a color stop
+    return match<string, Gradient>(name)
+      .with("linear-gradient", () => {
+        const hasPrelude = tokens[0] === "to" || isAngle(tokens[0]);
+        const direction = toLinearDirection(hasPrelude ? tokens : ["to", "bottom"]);
+        return { kind: { Linear: direction }, stops: toColorStops(hasPrelude ? rest : [first, ...rest]) };
+      })
+      .with("radial-gradient", () => {
+        const hasPrelude = tokens[0] === "at" || tokens[0] in radialKeywords || isLength(tokens[0]);
+        const radial = toRadial(hasPrelude ? tokens : []);
+        return { kind: { Radial: radial }, stops: toColorStops(hasPrelude ? rest : [first, ...rest]) };
+      })
+      .with("conic-gradient", () => {
+        const hasPrelude = tokens[0] === "from" || tokens[0] === "at";
+        const conic = toConic(hasPrelude ? tokens : []);
+        return { kind: { Conic: conic }, stops: toColorStops(hasPrelude ? rest : [first, ...rest], toConicStop) };
+      })
+      .otherwise(unknownProp("backgroundImage", layer));
+  });
+}
+
+// splits on a separator outside of parentheses, so colors like rgb(0, 0, 0) stay whole
+export function splitTopLevel(value: string, separator: string): string[] {
+  const parts: string[] = [];
+  let depth = 0;
+  let current = "";
+
+  for (const char of value) {
+    if (char === "(") depth++;
+    if (char === ")") depth--;
+
+    if (char === separator && depth === 0) {
+      parts.push(current);
+      current = "";
+    } else {
+      current += char;
+    }
+  }
+
+  parts.push(current);
+  return parts;
+}
+
 /*
  * to values
  */
@@ -305,9 +491,21 @@ function toOverflow(value: string): Overflow {
     .with("hidden", () => "Hidden")
     .with("clip", () => "Clip")
     .with("scroll", () => "Scroll")
+    // scrolls without a gutter, see overlayScrollbarWidthFromCss
+    .with("auto", () => "Scroll")
     .otherwise(unknownProp("overflow", value));
 }
 
+function toScrollbarWidth(value: string | number): number {
+  return match(value)
+    .with("auto", () => SCROLLBAR_WIDTH)
+    .with("thin", () => SCROLLBAR_WIDTH / 2)
+    .with("none", () => 0)
+    .with(P.string.endsWith("px"), v => parseFloat(v))
+    .with(P.number, v => v)
+    .otherwise(unknownProp("scrollbarWidth", value));
+}
+
 function toPosition(value: string): Position {
   return match<string, Position>(value)
     .with("absolute", () => "Absolute")
@@ -384,6 +582,166 @@ function toLengthPercentage(value: string | number): LengthPercentage {
     .otherwise(unknownValue(value));
 }
 
+function toTransformFunction(name: string, args: string[]): TransformFunction {
+  const [x, y] = args;
+  const length = (value: string | undefined) => toLengthPercentage(toUnitless(value ?? "0"));
+  const number = (value: string | undefined) => parseFloat(value ?? "0");
+
+  return match<string, TransformFunction>(name)
+    .with("translate", () => ({ Translate: [length(x), length(y)] }))
+    .with("translateX", () => ({ Translate: [length(x), toLength(0)] }))
+    .with("translateY", () => ({ Translate: [toLength(0), length(x)] }))
+    .with("scale", () => ({ Scale: [number(x), number(y ?? x)] }))
+    .with("scaleX", () => ({ Scale: [number(x), 1] }))
+    .with("scaleY", () => ({ Scale: [1, number(x)] }))
+    .with("rotate", () => ({ Rotate: toAngle(x) }))
+    .with("skew", () => ({ Skew: [toAngle(x), toAngle(y ?? "0")] }))
+    .with("skewX", () => ({ Skew: [toAngle(x), 0] }))
+    .with("skewY", () => ({ Skew: [0, toAngle(x)] }))
+    .with("matrix", () => ({
+      Matrix: z
+        .tuple([z.number(), z.number(), z.number(), z.number(), z.number(), z.number()])
+        .parse(args.map(number)),
+    }))
+    .otherwise(unknownProp("transform", `${name}(${args.join(", ")})`));
+}
+
+// radians
+function toAngle(value: string): number {
+  return match(value)
+    .with(P.string.endsWith("deg"), v => (parseFloat(v) * Math.PI) / 180)
+    .with(P.string.endsWith("grad"), v => (parseFloat(v) * Math.PI) / 200)
+    .with(P.string.endsWith("rad"), v => parseFloat(v))
+    .with(P.string.endsWith("turn"), v => parseFloat(v) * Math.PI * 2)
+    .with(P.string.regex(/^-?[\d.]+$/), v => parseFloat(v))
+    .otherwise(unknownValue(value));
+}
+
+// "to right", "to top left", "45deg"
+function toLinearDirection(tokens: string[]): LinearDirection {
+  if (tokens[0] !== "to") {
+    return { Angle: toAngle(tokens[0]) };
+  }
+
+  const sides = tokens.slice(1);
+  const x = sides.includes("left") ? -1 : sides.includes("right") ? 1 : 0;
+  const y = sides.includes("top") ? -1 : sides.includes("bottom") ? 1 : 0;
+
+  if (x !== 0 && y !== 0) {
+    return { Corner: [x, y] };
+  }
+
+  // to top is 0deg, clockwise from there
+  return { Angle: Math.atan2(x, -y) };
+}
+
+const radialKeywords: Record<string, RadialSize | RadialGradient["shape"]> = {
+  circle: "Circle",
+  ellipse: "Ellipse",
+  "closest-side": "ClosestSide",
+  "farthest-side": "FarthestSide",
+  "closest-corner": "ClosestCorner",
+  "farthest-corner": "FarthestCorner",
+};
+
+// "circle closest-side at 20% 30%", "40px 20px", "ellipse"
+function toRadial(tokens: string[]): RadialGradient {
+  const at = tokens.indexOf("at");
+  const [shapeAndSize, position] = at === -1 ? [tokens, []] : [tokens.slice(0, at), tokens.slice(at + 1)];
+  const lengths = shapeAndSize.filter(isLength).map(v => toLengthPercentage(toUnitless(v)));
+  const keywords = shapeAndSize
+    .filter(token => !isLength(token))
+    .map(token => radialKeywords[token] ?? unknownProp("backgroundImage", tokens.join(" "))());
+
+  // a single length is the radius of a circle, two are those of an ellipse
+  const circle = keywords.includes("Circle") || (!keywords.includes("Ellipse") && lengths.length === 1);
+  const [width, height = width] = lengths;
+  const keyword = keywords.find((keyword): keyword is RadialSize => keyword !== "Circle" && keyword !== "Ellipse");
+  const size: RadialSize = width !== undefined ? { Explicit: [width, height] } : (keyword ?? "FarthestCorner");
+
+  return { shape: circle ? "Circle" : "Ellipse", size, position: toGradientPosition(position) };
+}
+
+// "from 90deg at 25% 50%"
+function toConic(tokens: string[]): ConicGradient {
+  const from = tokens.indexOf("from");
+  const at = tokens.indexOf("at");
+
+  return {
+    from: from === -1 ? 0 : toAngle(tokens[from + 1]),
+    position: toGradientPosition(at === -1 ? [] : tokens.slice(at + 1)),
+  };
+}
+
+function toGradientPosition(tokens: string[]): Point<LengthPercentage> {
+  return toTransformOrigin(tokens.length === 0 ? "center" : tokens.join(" "));
+}
+
+// "red", "red 10%", "rgb(0 0 0 / 50%) 10px 20px", the last sets two stops of the same color
+function toColorStops(
+  stops: string[],
+  toPosition: (value: string) => LengthPercentage = v => toLengthPercentage(toUnitless(v))
+): ColorStop[] {
+  return stops.flatMap(stop => {
+    const tokens = splitTopLevel(stop.trim(), " ").filter(token => token !== "");
+    const positions = tokens.filter(token => isLength(token) || isAngle(token)).map(toPosition);
+    const color = tokens.filter(token => !isLength(token) && !isAngle(token)).join(" ");
+
+    if (color === "") {
+      throw new Error(`Unknown color stop "${stop}", color hints are not supported`);
+    }
+
+    return positions.length === 0 ? [{ color, position: null }] : positions.map(position => ({ color, position }));
+  });
+}
+
+// conic stops are angles or percentages of the full turn
+function toConicStop(value: string): LengthPercentage {
+  return value.endsWith("%") ? toLengthPercentage(value) : toPercent(toAngle(value) / (Math.PI * 2));
+}
+
+function isLength(token: string | undefined): boolean {
+  return token !== undefined && /^-?[\d.]+(px|%)?$/.test(token);
+}
+
+function isAngle(token: string | undefined): boolean {
+  return token !== undefined && /^-?[\d.]+(deg|grad|rad|turn)$/.test(token);
+}
+
+// "left top", "center", "10px 50%"
+function toTransformOrigin(value: string | number): Point<LengthPercentage> {
+  const keywords: Record<string, string> = {
+    left: "0%",
+    top: "0%",
+    center: "50%",
+    right: "100%",
+    bottom: "100%",
+  };
+  const values = typeof value === "number" ? [String(value)] : split(value.trim());
+  const [first, second = "center"] = values;
+
+  // keywords may come in either order, a vertical one first means y
+  const vertical = first === "top" || first === "bottom" || second === "left" || second === "right";
+  const [x, y] = vertical ? [second, first] : [first, second];
+
+  return pipe(
+    [keywords[x] ?? x, keywords[y] ?? y] as [string, string],
+    map2(v => toLengthPercentage(toUnitless(v))),
+    toPoint
+  );
+}
+
+function toCornerRadius(
+  value: unknown,
+  fallback: Size<LengthPercentage>
+): Size<LengthPercentage> {
+  if (value === undefined) {
+    return fallback;
+  }
+  const [width, height] = pipe(value, isStringOrNum, toShorthand2, map2(toLengthPercentage));
+  return { width, height };
+}
+
 function toAspectRatio(value: string | number): number {
   return match(value)
     .with(P.number, v => v)
@@ -542,10 +900,15 @@ function toMinMax<Min, Max>([min, max]: [Min, Max]): MinMax<Min, Max> {
   return { min, max };
 }
 
-function toRect<T>([left, right, top, bottom]: [T, T, T, T]): Rect<T> {
+// shorthands are in css order
+function toRect<T>([top, right, bottom, left]: [T, T, T, T]): Rect<T> {
   return { left, right, top, bottom };
 }
 
+function zeroRect(): Rect<LengthPercentage> {
+  return toRect([toLength(0), toLength(0), toLength(0), toLength(0)]);
+}
+
 function toAuto(): Auto {
   return "Auto";
 }
@@ -566,6 +929,11 @@ function map4<T, U>(fn: (value: T) => U): (values: [T, T, T, T]) => [U, U, U, U]
   return values => values.map(fn) as [U, U, U, U];
 }
 
+// unitless numbers like "0" are lengths in pixels
+function toUnitless(value: string): string | number {
+  return /^-?[\d.]+$/.test(value) ? parseFloat(value) : value;
+}
+
 function toShorthand2(value: string): [string, string];
 function toShorthand2(value: number): [number, number];
 function toShorthand2(value: string | number): [string, string] | [number, number];
@@ -0,0 +1,93 @@
+import { createElement } from "react";
+import type { CSSProperties, ReactNode } from "react";
+import type { GuiCloseEvent } from "./events.ts";
+
+// @ts-expect-error not typed yet
+const set_main_window_options = Deno.core.ops.op_set_main_window_options;
+
+// sizes and positions in logical pixels, like styles
+export interface WindowOptionProps {
+  title?: string;
+  width?: number;
+  height?: number;
+  minWidth?: number;
+  minHeight?: number;
+  maxWidth?: number;
+  maxHeight?: number;
+  position?: "center" | { x: number; y: number };
+  decorations?: boolean;
+  resizable?: boolean;
+  alwaysOnTop?: boolean;
+  // borderless on the monitor the window is on
+  fullscreen?: boolean;
+  // image file, relative to the entry script like image sources
+  icon?: string;
+}
+
+export interface WindowProps extends WindowOptionProps {
+  // for the root of the window, which fills it unless the size is set
+  style?: CSSProperties;
+  children?: ReactNode;
+  // the user closed the window, which should be unmounted then
+  onClose?: (event: GuiCloseEvent) => void;
+}
+
+// `WindowOptions` in window_options.rs, where undefined leaves an option as it is
+export interface WindowOptions {
+  title?: string;
+  size?: [number, number];
+  min_size?: [number, number];
+  max_size?: [number, number];
+  position?: "Center" | { At: [number, number] };
+  decorations?: boolean;
+  resizable?: boolean;
+  always_on_top?: boolean;
+  fullscreen?: boolean;
+  icon?: string;
+}
+
+/**
+ * Shows the children in a window of its own, which closes when this
+ * unmounts. It takes no space where it is rendered.
+ */
+export function Window({ style = {}, ...props }: WindowProps) {
+  return createElement("window", { ...props, style });
+}
+
+// the main window opens with the app, so its options change it afterwards
+export function setMainWindowOptions(props: WindowOptionProps) {
+  set_main_window_options(toWindowOptions(props));
+}
+
+export function toWindowOptions(props: WindowOptionProps): WindowOptions {
+  const { title, position, decorations, resizable, alwaysOnTop, fullscreen, icon } = props;
+
+  return {
+    title,
+    size: toSize(props.width, props.height),
+    min_size: toSize(props.minWidth, props.minHeight),
+    max_size: toSize(props.maxWidth, props.maxHeight),
+    position: position === undefined ? undefined : position === "center" ? "Center" : { At: [position.x, position.y] },
+    decorations,
+    resizable,
+    always_on_top: alwaysOnTop,
+    fullscreen,
+    icon,
+  };
+}
+
+// the user may have moved or resized the window since, so only changed options are set again
+export function changedWindowOptions(oldProps: WindowOptionProps, newProps: WindowOptionProps): WindowOptions | null {
+  const oldOptions = toWindowOptions(oldProps);
+  const newOptions = toWindowOptions(newProps);
+  const keys = (Object.keys(newOptions) as (keyof WindowOptions)[]).filter(
+    key => newOptions[key] !== undefined && JSON.stringify(newOptions[key]) !== JSON.stringify(oldOptions[key])
+  );
+
+  return keys.length === 0 ? null : (Object.fromEntries(keys.map(key => [key, newOptions[key]])) as WindowOptions);
+}
+
+// a size needs both sides, the window keeps its own otherwise
+function toSize(width: number | undefined, height: number | undefined): [number, number] | undefined {
+  return width === undefined || height === undefined ? undefined : [width, height];
+}
@@ -1,24 +1,141 @@
+use std::env;
+use std::process::ExitCode;
+use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-use winit::error::EventLoopError;
 use winit::event_loop::EventLoop;
 
 use crate::app::App;
 use crate::app::CustomEvent;
+use crate::app::EventSender;
+use crate::cli::Command;
+use crate::cli::Options;
+use crate::gui::Gui;
+use crate::headless::Headless;
 use crate::javascript_runtime::run_script;
+use crate::window_options::WindowOptions;
 
+mod animation;
 mod app;
+mod cli;
+mod events;
 mod gpu;
+mod gradient;
 mod gui;
+mod headless;
+mod images;
 mod javascript_runtime;
+mod text;
+mod transform;
+mod window_options;
 
-fn main() -> Result<(), EventLoopError> {
-    let event_loop = EventLoop::<CustomEvent>::with_user_event().build()?;
-    let event_loop_proxy = Arc::new(Mutex::new(event_loop.create_proxy()));
-    let mut app = App::new(event_loop_proxy);
+fn main() -> ExitCode {
+    let options = match cli::parse(env::args_os().skip(1)) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("Run with --help for usage");
+            return ExitCode::from(error.exit_code());
+        }
+    };
 
-    run_script(app.gui.clone(), "src/main.tsx");
+    if options.headless {
+        run_headless(options)
+    } else {
+        run_windowed(options)
+    }
+}
+
+fn run_windowed(options: Options) -> ExitCode {
+    let event_loop = match EventLoop::<CustomEvent>::with_user_event().build() {
+        Ok(event_loop) => event_loop,
+        Err(error) => {
+            eprintln!("Failed to start the event loop: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let events = EventSender::EventLoop(event_loop.create_proxy());
+    let (script_tx, script_rx) = mpsc::channel();
+
+    // the platform places the window unless the script sets a position
+    let mut window_options = WindowOptions::default().with_title(window_title(&options));
+    if let Some([width, height]) = options.size {
+        window_options = window_options.with_size(width, height);
+    }
+
+    let mut app = App::new(Arc::new(Mutex::new(events.clone())), script_tx.clone())
+        .with_window_options(window_options)
+        .with_backends(options.backends)
+        .with_watch(options.watch);
+
+    run_script(
+        app.gui.clone(),
+        events,
+        script_tx,
+        script_rx,
+        options.script,
+        options.watch,
+    );
+
+    if let Err(error) = event_loop.run_app(&mut app) {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+
+    app.exit_code()
+}
+
+/// The name of the entry script, which `<Window title>` can replace
+fn window_title(options: &Options) -> String {
+    options
+        .script
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string())
+}
+
+fn run_headless(options: Options) -> ExitCode {
+    let (events_tx, events_rx) = mpsc::channel();
+    let events = EventSender::Channel(events_tx);
+    let (script_tx, script_rx) = mpsc::channel();
+
+    let gui = Arc::new(Mutex::new(Gui::new(Arc::new(Mutex::new(events.clone())))));
+    let size = options.size.unwrap_or(headless::DEFAULT_SIZE);
+
+    let headless = match Headless::new(
+        gui.clone(),
+        events_rx,
+        script_tx.clone(),
+        size,
+        options.backends,
+    ) {
+        Ok(headless) => headless
+            .with_output(options.output)
+            .with_watch(options.watch),
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    run_script(
+        gui,
+        events,
+        script_tx,
+        script_rx,
+        options.script,
+        options.watch,
+    );
 
-    event_loop.run_app(&mut app)
+    headless.run()
 }
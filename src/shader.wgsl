@@ -1,21 +1,77 @@
 var<push_constant> viewport: vec2<f32>;
 
+@group(0) @binding(0) var image_atlas: texture_2d<f32>;
+@group(0) @binding(1) var image_sampler: sampler;
+
+const INSTANCE_RECT: u32 = 0u;
+const INSTANCE_OUTER_SHADOW: u32 = 1u;
+const INSTANCE_INSET_SHADOW: u32 = 2u;
+
+const BORDER_SOLID: u32 = 0u;
+const BORDER_DASHED: u32 = 1u;
+const BORDER_DOTTED: u32 = 2u;
+const BORDER_NONE: u32 = 3u;
+
+const GRADIENT_LINEAR: u32 = 0u;
+const GRADIENT_RADIAL: u32 = 1u;
+const GRADIENT_CONIC: u32 = 2u;
+
+const TAU: f32 = 6.28318530718;
+
+// Same layout as `Instance` in gpu.rs
+struct Instance {
+    rect: vec4<f32>,
+    background_color: vec4<f32>,
+    border_radii_x: vec4<f32>,
+    border_radii_y: vec4<f32>,
+    image_rect: vec4<f32>,
+    uv_rect: vec4<f32>,
+    border_widths: vec4<f32>,
+    border_colors: array<vec4<f32>, 4>,
+    shadow_rect: vec4<f32>,
+    shadow_radii_x: vec4<f32>,
+    shadow_radii_y: vec4<f32>,
+    // rows of the affine matrix mapping the rect into the window
+    transform: array<vec4<f32>, 2>,
+    // outset of the quad beyond rect, shadow blur sigma, opacity
+    params: vec4<f32>,
+    // instance kind, border style, clip, gradients
+    flags: vec4<u32>,
+};
+
+// Same layout as `GradientLayer` in gpu.rs
+struct Gradient {
+    // start and end of a linear gradient, center and radii of a radial one,
+    // center and start angle of a conic one
+    geometry: vec4<f32>,
+    // gradient kind, first stop, stop count, layer above
+    info: vec4<u32>,
+};
+
+// Same layout as `GradientStop` in gpu.rs
+struct GradientStop {
+    // premultiplied oklab
+    color: vec4<f32>,
+    // offset along the gradient in x
+    offset: vec4<f32>,
+};
+
+@group(1) @binding(0) var<storage, read> instances: array<Instance>;
+@group(1) @binding(2) var<storage, read> gradients: array<Gradient>;
+@group(1) @binding(3) var<storage, read> gradient_stops: array<GradientStop>;
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) background_color: vec4<f32>,
-    @location(1) border_radius: f32,
-    @location(2) rect_pos: vec2<f32>,
-    @location(3) rect_size: vec2<f32>,
+    @location(0) @interpolate(flat) instance_index: u32,
+    @location(1) rect_pos: vec2<f32>,
 };
 
 @vertex
 fn vs_main(
     @builtin(vertex_index) vertex_index: u32,
-    @location(0) instance_pos: vec2<f32>,
-    @location(1) instance_size: vec2<f32>,
-    @location(2) background_color: vec4<f32>,
-    @location(3) border_radius: f32,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
+    let instance = instances[instance_index];
 
     var vertex_pos: vec2<f32>;
     switch vertex_index % 6u {
@@ -27,36 +83,260 @@ fn vs_main(
         case 5u, default: { vertex_pos = vec2<f32>(1.0, 1.0); }  // right top
     }
 
-    let pos = instance_pos + vertex_pos * instance_size;
+    // Outer shadows reach beyond the rect they belong to
+    let outset = instance.params.x;
+    let rect_pos = vertex_pos * (instance.rect.zw + outset * 2.0) - outset;
+
+    let local_pos = vec3<f32>(instance.rect.xy + rect_pos, 1.0);
+    let pos = vec2<f32>(
+        dot(instance.transform[0].xyz, local_pos),
+        dot(instance.transform[1].xyz, local_pos),
+    );
     let ndc_x = (pos.x / viewport.x) * 2.0 - 1.0;
     let ndc_y = 1.0 - (pos.y / viewport.y) * 2.0;
 
     var output: VertexOutput;
 
     output.clip_position = vec4<f32>(ndc_x, ndc_y, 1.0, 1.0);
-    output.background_color = background_color;
-    output.border_radius = border_radius;
-    output.rect_pos = vertex_pos * instance_size;
-    output.rect_size = instance_size;
+    output.instance_index = instance_index;
+    output.rect_pos = rect_pos;
 
     return output;
 }
 
-@fragment 
+// Approximation from https://madebyevan.com/shaders/fast-rounded-rectangle-shadows/
+fn erf(x: f32) -> f32 {
+    let s = sign(x);
+    let a = abs(x);
+    var y = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    y *= y;
+    return s - s / (y * y);
+}
+
+// Coverage of a rounded rect convolved with a gaussian, treating the edge
+// as a straight line at the signed distance
+fn blurred_coverage(distance: f32, sigma: f32) -> f32 {
+    if (sigma <= 0.0) {
+        return clamp(0.5 - distance, 0.0, 1.0);
+    }
+    return 0.5 - 0.5 * erf(distance / (sigma * sqrt(2.0)));
+}
+
+// "source over" for straight alpha colors
+fn blend_over(src: vec4<f32>, dst: vec4<f32>) -> vec4<f32> {
+    let alpha = src.a + dst.a * (1.0 - src.a);
+    if (alpha <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>((src.rgb * src.a + dst.rgb * dst.a * (1.0 - src.a)) / alpha, alpha);
+}
+
+// Coverage of the dash or dot pattern at `along` the side and `across` it,
+// measured from the outer edge
+fn border_pattern(style: u32, width: f32, along: f32, across: f32) -> f32 {
+    switch style {
+        case BORDER_DASHED: {
+            return select(0.0, 1.0, along % (width * 6.0) < width * 3.0);
+        }
+        case BORDER_DOTTED: {
+            let along_dot = along % (width * 2.0) - width * 0.5;
+            let dot = length(vec2<f32>(along_dot, across - width * 0.5));
+            return clamp(width * 0.5 - dot + 0.5, 0.0, 1.0);
+        }
+        default: {
+            return 1.0;
+        }
+    }
+}
+
+// Where the rect position falls along the gradient, 0 at the start and 1 at the end
+fn gradient_offset(gradient: Gradient, rect_pos: vec2<f32>) -> f32 {
+    let geometry = gradient.geometry;
+
+    switch gradient.info.x {
+        case GRADIENT_RADIAL: {
+            return length((rect_pos - geometry.xy) / max(geometry.zw, vec2<f32>(1e-4)));
+        }
+        case GRADIENT_CONIC: {
+            // clockwise from the top, like css
+            let from_center = rect_pos - geometry.xy;
+            return fract((atan2(from_center.x, -from_center.y) - geometry.z) / TAU);
+        }
+        default: {
+            let line = geometry.zw - geometry.xy;
+            return dot(rect_pos - geometry.xy, line) / max(dot(line, line), 1e-8);
+        }
+    }
+}
+
+// Interpolates between the stops around the offset in premultiplied oklab,
+// the ends extend past the first and last stop
+fn gradient_color(gradient: Gradient, offset: f32) -> vec4<f32> {
+    let first = gradient.info.y;
+    let count = gradient.info.z;
+    var color = gradient_stops[first].color;
+
+    for (var index = first + 1u; index < first + count; index++) {
+        let previous = gradient_stops[index - 1u];
+        let stop = gradient_stops[index];
+
+        if (offset >= stop.offset.x) {
+            color = stop.color;
+            continue;
+        }
+
+        if (offset > previous.offset.x) {
+            let amount = (offset - previous.offset.x) / (stop.offset.x - previous.offset.x);
+            color = mix(previous.color, stop.color, amount);
+        }
+        break;
+    }
+
+    return oklab_to_srgb(color);
+}
+
+// https://bottosson.github.io/posts/oklab/, to the same gamma encoded srgb
+// as the other colors
+fn oklab_to_srgb(premultiplied: vec4<f32>) -> vec4<f32> {
+    if (premultiplied.a <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+
+    let lab = premultiplied.rgb / premultiplied.a;
+    let lms = vec3<f32>(
+        lab.x + 0.3963377774 * lab.y + 0.2158037573 * lab.z,
+        lab.x - 0.1055613458 * lab.y - 0.0638541728 * lab.z,
+        lab.x - 0.0894841775 * lab.y - 1.2914855480 * lab.z,
+    );
+    let cubed = lms * lms * lms;
+    let linear = clamp(vec3<f32>(
+        4.0767416621 * cubed.x - 3.3077115913 * cubed.y + 0.2309699292 * cubed.z,
+        -1.2684380046 * cubed.x + 2.6097574011 * cubed.y - 0.3413193965 * cubed.z,
+        -0.0041960863 * cubed.x - 0.7034186147 * cubed.y + 1.7076147010 * cubed.z,
+    ), vec3<f32>(0.0), vec3<f32>(1.0));
+
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return vec4<f32>(select(high, low, linear <= vec3<f32>(0.0031308)), premultiplied.a);
+}
+
+fn shadow_color(instance: Instance, rect_pos: vec2<f32>, shape_alpha: f32, pixel_size: f32) -> vec4<f32> {
+    let sigma = instance.params.y;
+    let shadow_distance = rounded_rect_sdf(
+        rect_pos - instance.shadow_rect.xy,
+        instance.shadow_rect.zw,
+        instance.shadow_radii_x,
+        instance.shadow_radii_y,
+    );
+    let shadow = blurred_coverage(shadow_distance / pixel_size, sigma / pixel_size);
+
+    // Outer shadows only show outside of the rect, inset shadows only inside
+    // of it and outside of the shadow shape
+    var coverage: f32;
+    if (instance.flags.x == INSTANCE_INSET_SHADOW) {
+        coverage = (1.0 - shadow) * shape_alpha;
+    } else {
+        coverage = shadow * (1.0 - shape_alpha);
+    }
+
+    let color = instance.background_color;
+    return vec4<f32>(color.rgb, color.a * coverage * instance.params.z);
+}
+
+@fragment
 fn fs_main(vs_output: VertexOutput) -> @location(0) vec4f {
-    let radius = vs_output.border_radius;
-    let rect_size = vs_output.rect_size;
+    let instance = instances[vs_output.instance_index];
+    let rect_size = instance.rect.zw;
     let rect_pos = vs_output.rect_pos;
-    
-    // Calculate distance from corners for rounded rectangle
-    let corner_radius = min(radius, min(rect_size.x, rect_size.y) * 0.5);
-    
-    // Calculate the distance to the nearest corner
-    let corner_distance = length(max(abs(rect_pos - rect_size * 0.5) - (rect_size * 0.5 - corner_radius), vec2<f32>(0.0, 0.0)));
-    
+    let radii_x = instance.border_radii_x;
+    let radii_y = instance.border_radii_y;
+
+    // top, right, bottom, left
+    let widths = instance.border_widths;
+
+    // Size of a physical pixel in rect units, which differ from pixels once
+    // scaled by a transform or the scale factor of the display, so the edges
+    // fade over one physical pixel
+    let pixel_size = max(length(fwidth(rect_pos)) * 0.70710678, 1e-4);
+
     // Anti-aliased edge
-    let edge_softness = 1.0;
-    let alpha = 1.0 - smoothstep(corner_radius - edge_softness, corner_radius, corner_distance);
-    
-    return vec4<f32>(vs_output.background_color.rgb, vs_output.background_color.a * alpha);
-}
\ No newline at end of file
+    let outer_distance = rounded_rect_sdf(rect_pos, rect_size, radii_x, radii_y);
+    let alpha = clamp(0.5 - outer_distance / pixel_size, 0.0, 1.0);
+
+    if (instance.flags.x != INSTANCE_RECT) {
+        let shadow = shadow_color(instance, rect_pos, alpha, pixel_size);
+        let clip_alpha = clip_coverage(instance.flags.z, vs_output.clip_position.xy);
+        return vec4<f32>(shadow.rgb, shadow.a * clip_alpha);
+    }
+
+    var color = instance.background_color;
+
+    // Gradients over the background color, from the bottom layer up
+    var gradient_index = instance.flags.w;
+    while (gradient_index != 0u) {
+        let gradient = gradients[gradient_index - 1u];
+        let layer_color = gradient_color(gradient, gradient_offset(gradient, rect_pos));
+
+        color = blend_over(layer_color, color);
+        gradient_index = gradient.info.w;
+    }
+
+    // Image drawn over the background, the uv size is zero until it is in the atlas
+    let image_pos = instance.image_rect.xy;
+    let image_size = instance.image_rect.zw;
+    let uv_pos = instance.uv_rect.xy;
+    let uv_size = instance.uv_rect.zw;
+    let image_local = (rect_pos - image_pos) / image_size;
+
+    if (uv_size.x > 0.0 && all(image_local >= vec2<f32>(0.0)) && all(image_local <= vec2<f32>(1.0))) {
+        // Stay half a texel inside the image so neighbours in the atlas do not bleed in
+        let half_texel = 0.5 / vec2<f32>(textureDimensions(image_atlas));
+        let uv = clamp(uv_pos + image_local * uv_size, uv_pos + half_texel, uv_pos + uv_size - half_texel);
+        let image_color = textureSampleLevel(image_atlas, image_sampler, uv, 0.0);
+
+        color = blend_over(image_color, color);
+    }
+
+    // Borders, the inner edge follows the outer radius minus the border widths
+    let border_style = instance.flags.y;
+    if (border_style != BORDER_NONE && any(widths > vec4<f32>(0.0))) {
+        let inner_pos = rect_pos - widths.wx;
+        let inner_size = max(rect_size - widths.yx - widths.wz, vec2<f32>(0.0));
+        let inner_radii_x = max(radii_x - widths.wyyw, vec4<f32>(0.0));
+        let inner_radii_y = max(radii_y - widths.xxzz, vec4<f32>(0.0));
+        let inner_distance = rounded_rect_sdf(inner_pos, inner_size, inner_radii_x, inner_radii_y);
+        let border_coverage = clamp(0.5 + inner_distance / pixel_size, 0.0, 1.0);
+
+        if (border_coverage > 0.0) {
+            // The closest side relative to its width wins, which splits the
+            // corners diagonally like css
+            let distances = vec4<f32>(
+                rect_pos.y,
+                rect_size.x - rect_pos.x,
+                rect_size.y - rect_pos.y,
+                rect_pos.x,
+            ) / max(widths, vec4<f32>(1e-4));
+
+            var side = 0u;
+            var along = rect_pos.x;
+            if (distances.y < distances[side]) { side = 1u; along = rect_pos.y; }
+            if (distances.z < distances[side]) { side = 2u; along = rect_pos.x; }
+            if (distances.w < distances[side]) { side = 3u; along = rect_pos.y; }
+
+            let width = widths[side];
+            let across = distances[side] * width;
+            let pattern = border_pattern(border_style, width, along, across);
+
+            // arrays can only be indexed dynamically through the storage buffer
+            let border_color = instances[vs_output.instance_index].border_colors[side];
+            color = blend_over(
+                vec4<f32>(border_color.rgb, border_color.a * border_coverage * pattern),
+                color,
+            );
+        }
+    }
+
+    let clip_alpha = clip_coverage(instance.flags.z, vs_output.clip_position.xy);
+
+    return vec4<f32>(color.rgb, color.a * alpha * instance.params.z * clip_alpha);
+}
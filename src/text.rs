@@ -0,0 +1,142 @@
+use cosmic_text::{
+    Attrs, Buffer, CacheKey, FontSystem, Metrics, Shaping, SwashCache, SwashImage, Wrap,
+};
+use taffy::{AvailableSpace, Size};
+
+use crate::gpu::ClipRegion;
+use crate::transform::Affine;
+
+pub struct TextSystem {
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+    /// Glyphs are rasterized this many times larger than their logical
+    /// size, to stay sharp on the physical pixels of the window
+    scale_factor: f32,
+}
+
+impl TextSystem {
+    pub fn new() -> Self {
+        Self {
+            font_system: FontSystem::new(),
+            swash_cache: SwashCache::new(),
+            scale_factor: 1.0,
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+    }
+
+    pub fn rasterize(&mut self, cache_key: CacheKey) -> Option<SwashImage> {
+        self.swash_cache
+            .get_image_uncached(&mut self.font_system, cache_key)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Glyph {
+    pub cache_key: CacheKey,
+    pub x: f32,
+    pub y: f32,
+    pub color: [f32; 4],
+    pub transform: Affine,
+    pub clip: ClipRegion,
+}
+
+pub struct Text {
+    buffer: Buffer,
+    color: [f32; 4],
+}
+
+impl Text {
+    pub fn new(
+        text_system: &mut TextSystem,
+        content: &str,
+        color: [f32; 4],
+        font_size: f32,
+        line_height: f32,
+    ) -> Self {
+        let font_system = &mut text_system.font_system;
+
+        // cosmic-text panics on a font size of 0
+        let metrics = Metrics::new(font_size.max(1.0), line_height.max(1.0));
+
+        let mut buffer = Buffer::new(font_system, metrics);
+        buffer.set_wrap(font_system, Wrap::Word);
+        buffer.set_text(font_system, content, Attrs::new(), Shaping::Advanced);
+
+        Self { buffer, color }
+    }
+
+    pub fn measure(
+        &mut self,
+        text_system: &mut TextSystem,
+        known_dimensions: Size<Option<f32>>,
+        available_space: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        if let Size {
+            width: Some(width),
+            height: Some(height),
+        } = known_dimensions
+        {
+            return Size { width, height };
+        }
+
+        let width_constraint = known_dimensions.width.or(match available_space.width {
+            AvailableSpace::MinContent => Some(0.0),
+            AvailableSpace::MaxContent => None,
+            AvailableSpace::Definite(width) => Some(width),
+        });
+
+        self.buffer
+            .set_size(&mut text_system.font_system, width_constraint, None);
+
+        let (width, height) = self
+            .buffer
+            .layout_runs()
+            .fold((0.0f32, 0.0f32), |(width, height), run| {
+                (width.max(run.line_w), height + run.line_height)
+            });
+
+        Size {
+            width: known_dimensions.width.unwrap_or(width),
+            height: known_dimensions.height.unwrap_or(height),
+        }
+    }
+
+    /// Lays out the glyphs relative to the content box, which `transform`
+    /// maps into the window
+    pub fn collect_glyphs(
+        &mut self,
+        text_system: &mut TextSystem,
+        width: f32,
+        transform: Affine,
+        opacity: f32,
+        clip: ClipRegion,
+        glyphs: &mut Vec<Glyph>,
+    ) {
+        let [red, green, blue, alpha] = self.color;
+
+        self.buffer
+            .set_size(&mut text_system.font_system, Some(width), None);
+
+        // glyph positions and sizes come in physical pixels
+        let scale_factor = text_system.scale_factor;
+        let transform = transform.multiply(Affine::scale(1.0 / scale_factor, 1.0 / scale_factor));
+
+        for run in self.buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let physical = glyph.physical((0.0, run.line_y), scale_factor);
+
+                glyphs.push(Glyph {
+                    cache_key: physical.cache_key,
+                    x: physical.x as f32,
+                    y: physical.y as f32,
+                    color: [red, green, blue, alpha * opacity],
+                    transform,
+                    clip,
+                });
+            }
+        }
+    }
+}
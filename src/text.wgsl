@@ -0,0 +1,58 @@
+var<push_constant> viewport: vec2<f32>;
+
+@group(0) @binding(0) var atlas_texture: texture_2d<f32>;
+@group(0) @binding(1) var atlas_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) @interpolate(flat) clip: u32,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) instance_pos: vec2<f32>,
+    @location(1) instance_size: vec2<f32>,
+    @location(2) uv_pos: vec2<f32>,
+    @location(3) uv_size: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    // rows of the affine matrix mapping the glyph into the window
+    @location(5) transform_x: vec4<f32>,
+    @location(6) transform_y: vec4<f32>,
+    @location(7) clip: u32,
+) -> VertexOutput {
+
+    var vertex_pos: vec2<f32>;
+    switch vertex_index % 6u {
+        case 0u: { vertex_pos = vec2<f32>(0.0, 1.0); }           // left top
+        case 1u: { vertex_pos = vec2<f32>(0.0, 0.0); }           // left bottom
+        case 2u: { vertex_pos = vec2<f32>(1.0, 0.0); }           // right bottom
+        case 3u: { vertex_pos = vec2<f32>(0.0, 1.0); }           // left top
+        case 4u: { vertex_pos = vec2<f32>(1.0, 0.0); }           // right bottom
+        case 5u, default: { vertex_pos = vec2<f32>(1.0, 1.0); }  // right top
+    }
+
+    let local_pos = vec3<f32>(instance_pos + vertex_pos * instance_size, 1.0);
+    let pos = vec2<f32>(dot(transform_x.xyz, local_pos), dot(transform_y.xyz, local_pos));
+    let ndc_x = (pos.x / viewport.x) * 2.0 - 1.0;
+    let ndc_y = 1.0 - (pos.y / viewport.y) * 2.0;
+
+    var output: VertexOutput;
+
+    output.clip_position = vec4<f32>(ndc_x, ndc_y, 1.0, 1.0);
+    output.color = color;
+    output.uv = uv_pos + vertex_pos * uv_size;
+    output.clip = clip;
+
+    return output;
+}
+
+@fragment
+fn fs_main(vs_output: VertexOutput) -> @location(0) vec4f {
+    let coverage = textureSample(atlas_texture, atlas_sampler, vs_output.uv).r;
+    let clip_alpha = clip_coverage(vs_output.clip, vs_output.clip_position.xy);
+
+    return vec4<f32>(vs_output.color.rgb, vs_output.color.a * coverage * clip_alpha);
+}
@@ -0,0 +1,254 @@
+use serde::Deserialize;
+use taffy::prelude::*;
+use taffy::Point;
+
+/// 2D affine matrix in the order of css `matrix(a, b, c, d, e, f)`, mapping
+/// (x, y) to (a * x + c * y + e, b * x + d * y + f)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Affine(pub [f32; 6]);
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub fn translate(x: f32, y: f32) -> Self {
+        Affine([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Affine([x, 0.0, 0.0, y, 0.0, 0.0])
+    }
+
+    /// Clockwise in screen space, like css
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Affine([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    pub fn skew(x: f32, y: f32) -> Self {
+        Affine([1.0, y.tan(), x.tan(), 1.0, 0.0, 0.0])
+    }
+
+    /// Applies `other` first and then `self`
+    pub fn multiply(self, other: Affine) -> Self {
+        let [a1, b1, c1, d1, e1, f1] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+
+        Affine([
+            a1 * a2 + c1 * b2,
+            b1 * a2 + d1 * b2,
+            a1 * c2 + c1 * d2,
+            b1 * c2 + d1 * d2,
+            a1 * e2 + c1 * f2 + e1,
+            b1 * e2 + d1 * f2 + f1,
+        ])
+    }
+
+    pub fn apply(self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// None when the matrix collapses everything onto a line or a point,
+    /// e.g. `scale(0)`
+    pub fn invert(self) -> Option<Self> {
+        let [a, b, c, d, e, f] = self.0;
+        let determinant = a * d - b * c;
+
+        if determinant.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+
+        Some(Affine([
+            d * inverse,
+            -b * inverse,
+            -c * inverse,
+            a * inverse,
+            (c * f - d * e) * inverse,
+            (b * e - a * f) * inverse,
+        ]))
+    }
+
+    /// Rows as laid out for the shaders, `[a, c, e, 0]` and `[b, d, f, 0]`
+    pub fn to_rows(self) -> [[f32; 4]; 2] {
+        let [a, b, c, d, e, f] = self.0;
+        [[a, c, e, 0.0], [b, d, f, 0.0]]
+    }
+}
+
+/// A css transform function, angles are in radians
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub enum TransformFunction {
+    Translate(LengthPercentage, LengthPercentage),
+    Scale(f32, f32),
+    Rotate(f32),
+    Skew(f32, f32),
+    Matrix([f32; 6]),
+}
+
+impl TransformFunction {
+    pub fn resolve(self, size: Size<f32>) -> Affine {
+        match self {
+            TransformFunction::Translate(x, y) => Affine::translate(
+                resolve_length(x, size.width),
+                resolve_length(y, size.height),
+            ),
+            TransformFunction::Scale(x, y) => Affine::scale(x, y),
+            TransformFunction::Rotate(angle) => Affine::rotate(angle),
+            TransformFunction::Skew(x, y) => Affine::skew(x, y),
+            TransformFunction::Matrix(matrix) => Affine(matrix),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transform {
+    pub functions: Vec<TransformFunction>,
+    pub origin: Point<LengthPercentage>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            functions: Vec::new(),
+            origin: Point {
+                x: LengthPercentage::Percent(0.5),
+                y: LengthPercentage::Percent(0.5),
+            },
+        }
+    }
+}
+
+impl Transform {
+    /// The matrix for a box of `size`, around the transform origin. Like in
+    /// css the functions apply right to left.
+    pub fn resolve(&self, size: Size<f32>) -> Affine {
+        if self.functions.is_empty() {
+            return Affine::IDENTITY;
+        }
+
+        let origin_x = resolve_length(self.origin.x, size.width);
+        let origin_y = resolve_length(self.origin.y, size.height);
+
+        let matrix = self
+            .functions
+            .iter()
+            .fold(Affine::IDENTITY, |matrix, function| {
+                matrix.multiply(function.resolve(size))
+            });
+
+        Affine::translate(origin_x, origin_y)
+            .multiply(matrix)
+            .multiply(Affine::translate(-origin_x, -origin_y))
+    }
+}
+
+fn resolve_length(length: LengthPercentage, basis: f32) -> f32 {
+    match length {
+        LengthPercentage::Length(value) => value,
+        LengthPercentage::Percent(fraction) => fraction * basis,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    const SIZE: Size<f32> = Size {
+        width: 200.0,
+        height: 100.0,
+    };
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-4 && (actual.1 - expected.1).abs() < 1e-4,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn transform(functions: Vec<TransformFunction>) -> Transform {
+        Transform {
+            functions,
+            ..Transform::default()
+        }
+    }
+
+    #[test]
+    fn multiplies_applying_the_right_matrix_first() {
+        let matrix = Affine::translate(10.0, 0.0).multiply(Affine::scale(2.0, 2.0));
+        assert_close(matrix.apply(1.0, 1.0), (12.0, 2.0));
+
+        let matrix = Affine::scale(2.0, 2.0).multiply(Affine::translate(10.0, 0.0));
+        assert_close(matrix.apply(1.0, 1.0), (22.0, 2.0));
+    }
+
+    #[test]
+    fn rotates_clockwise_on_screen() {
+        // y points down, so clockwise turns the x axis onto the y axis
+        assert_close(Affine::rotate(FRAC_PI_2).apply(1.0, 0.0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn skews_along_each_axis() {
+        let angle = FRAC_PI_4;
+        assert_close(Affine::skew(angle, 0.0).apply(0.0, 1.0), (1.0, 1.0));
+        assert_close(Affine::skew(0.0, angle).apply(1.0, 0.0), (1.0, 1.0));
+    }
+
+    #[test]
+    fn inverts_matrices_that_do_not_collapse() {
+        let matrix = Affine::translate(5.0, -3.0)
+            .multiply(Affine::rotate(0.3))
+            .multiply(Affine::scale(2.0, 0.5));
+        let inverse = matrix.invert().unwrap();
+
+        let (x, y) = matrix.apply(7.0, 11.0);
+        assert_close(inverse.apply(x, y), (7.0, 11.0));
+
+        assert_eq!(Affine::scale(0.0, 1.0).invert(), None);
+        assert_eq!(Affine([1.0, 2.0, 2.0, 4.0, 0.0, 0.0]).invert(), None);
+    }
+
+    #[test]
+    fn resolves_percentages_against_the_box() {
+        let translate = TransformFunction::Translate(
+            LengthPercentage::Percent(0.5),
+            LengthPercentage::Length(-10.0),
+        );
+
+        assert_close(translate.resolve(SIZE).apply(0.0, 0.0), (100.0, -10.0));
+    }
+
+    #[test]
+    fn applies_functions_right_to_left_around_the_origin() {
+        // translate(10px, 0) rotate(90deg) around the center of 200x100
+        let rotated = transform(vec![
+            TransformFunction::Translate(
+                LengthPercentage::Length(10.0),
+                LengthPercentage::Length(0.0),
+            ),
+            TransformFunction::Rotate(FRAC_PI_2),
+        ])
+        .resolve(SIZE);
+
+        assert_close(rotated.apply(100.0, 50.0), (110.0, 50.0));
+        assert_close(rotated.apply(200.0, 50.0), (110.0, 150.0));
+
+        let top_left = Transform {
+            origin: Point {
+                x: LengthPercentage::Length(0.0),
+                y: LengthPercentage::Length(0.0),
+            },
+            ..transform(vec![TransformFunction::Scale(2.0, 3.0)])
+        };
+
+        assert_close(top_left.resolve(SIZE).apply(10.0, 10.0), (20.0, 30.0));
+    }
+
+    #[test]
+    fn resolves_no_functions_to_the_identity() {
+        assert_eq!(Transform::default().resolve(SIZE), Affine::IDENTITY);
+    }
+}
@@ -0,0 +1,208 @@
+use serde::Deserialize;
+use std::path::Path;
+use std::path::PathBuf;
+use winit::dpi::LogicalPosition;
+use winit::dpi::LogicalSize;
+use winit::dpi::PhysicalPosition;
+use winit::window::Fullscreen;
+use winit::window::Icon;
+use winit::window::Window;
+use winit::window::WindowAttributes;
+use winit::window::WindowLevel;
+
+/// Where a window is placed on the screen
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub enum WindowPosition {
+    /// In the middle of the monitor the window is on
+    Center,
+    /// Top left corner of the window frame, in logical pixels
+    At([f32; 2]),
+}
+
+/*
+ * Attributes of a window, set from rust with the `with_*` methods or from
+ * the script as props of `<Window>`. Options that are None are left as
+ * they are, so the same options serve to create a window and to change
+ * some of them later.
+ */
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WindowOptions {
+    pub title: Option<String>,
+    /// Inner size in logical pixels
+    pub size: Option<[f32; 2]>,
+    pub min_size: Option<[f32; 2]>,
+    pub max_size: Option<[f32; 2]>,
+    pub position: Option<WindowPosition>,
+    pub decorations: Option<bool>,
+    pub resizable: Option<bool>,
+    pub always_on_top: Option<bool>,
+    /// Borderless fullscreen on the monitor the window is on
+    pub fullscreen: Option<bool>,
+    /// Image file shown in the title bar and task bar, where the platform
+    /// has one
+    pub icon: Option<PathBuf>,
+}
+
+impl WindowOptions {
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.size = Some([width, height]);
+        self
+    }
+
+    /// The options set in `other` replace those set here
+    pub fn merge(self, other: WindowOptions) -> Self {
+        Self {
+            title: other.title.or(self.title),
+            size: other.size.or(self.size),
+            min_size: other.min_size.or(self.min_size),
+            max_size: other.max_size.or(self.max_size),
+            position: other.position.or(self.position),
+            decorations: other.decorations.or(self.decorations),
+            resizable: other.resizable.or(self.resizable),
+            always_on_top: other.always_on_top.or(self.always_on_top),
+            fullscreen: other.fullscreen.or(self.fullscreen),
+            icon: other.icon.or(self.icon),
+        }
+    }
+
+    /// Attributes to create the window with. Centering needs the monitor
+    /// the window ends up on, so `place` does it once the window exists.
+    pub fn to_attributes(&self) -> WindowAttributes {
+        let mut attributes = Window::default_attributes();
+
+        if let Some(title) = &self.title {
+            attributes = attributes.with_title(title);
+        }
+        if let Some([width, height]) = self.size {
+            attributes = attributes.with_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some([width, height]) = self.min_size {
+            attributes = attributes.with_min_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some([width, height]) = self.max_size {
+            attributes = attributes.with_max_inner_size(LogicalSize::new(width, height));
+        }
+        match self.position {
+            Some(WindowPosition::At([x, y])) => {
+                attributes = attributes.with_position(LogicalPosition::new(x, y));
+            }
+            Some(WindowPosition::Center) | None => (),
+        }
+        if let Some(decorations) = self.decorations {
+            attributes = attributes.with_decorations(decorations);
+        }
+        if let Some(resizable) = self.resizable {
+            attributes = attributes.with_resizable(resizable);
+        }
+        if let Some(always_on_top) = self.always_on_top {
+            attributes = attributes.with_window_level(window_level(always_on_top));
+        }
+        if let Some(fullscreen) = self.fullscreen {
+            attributes = attributes.with_fullscreen(borderless(fullscreen));
+        }
+        if let Some(icon) = &self.icon {
+            attributes = attributes.with_window_icon(load_icon(icon));
+        }
+
+        attributes
+    }
+
+    /// Moves a window created with `to_attributes` to the center when asked
+    pub fn place(&self, window: &Window) {
+        if self.position == Some(WindowPosition::Center) {
+            center(window);
+        }
+    }
+
+    /// Changes the options that are set on an open window
+    pub fn apply(&self, window: &Window) {
+        if let Some(title) = &self.title {
+            window.set_title(title);
+        }
+        if let Some([width, height]) = self.size {
+            // the window is laid out again once the resize arrives
+            let _ = window.request_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some([width, height]) = self.min_size {
+            window.set_min_inner_size(Some(LogicalSize::new(width, height)));
+        }
+        if let Some([width, height]) = self.max_size {
+            window.set_max_inner_size(Some(LogicalSize::new(width, height)));
+        }
+        match self.position {
+            Some(WindowPosition::Center) => center(window),
+            Some(WindowPosition::At([x, y])) => {
+                window.set_outer_position(LogicalPosition::new(x, y));
+            }
+            None => (),
+        }
+        if let Some(decorations) = self.decorations {
+            window.set_decorations(decorations);
+        }
+        if let Some(resizable) = self.resizable {
+            window.set_resizable(resizable);
+        }
+        if let Some(always_on_top) = self.always_on_top {
+            window.set_window_level(window_level(always_on_top));
+        }
+        if let Some(fullscreen) = self.fullscreen {
+            window.set_fullscreen(borderless(fullscreen));
+        }
+        if let Some(icon) = &self.icon {
+            window.set_window_icon(load_icon(icon));
+        }
+    }
+}
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    if always_on_top {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    }
+}
+
+fn borderless(fullscreen: bool) -> Option<Fullscreen> {
+    // None picks the monitor the window is on
+    fullscreen.then_some(Fullscreen::Borderless(None))
+}
+
+fn center(window: &Window) {
+    let Some(monitor) = window.current_monitor() else {
+        return;
+    };
+
+    let monitor_position = monitor.position();
+    let monitor_size = monitor.size();
+    let window_size = window.outer_size();
+
+    let x = monitor_position.x + (monitor_size.width as i32 - window_size.width as i32) / 2;
+    let y = monitor_position.y + (monitor_size.height as i32 - window_size.height as i32) / 2;
+
+    window.set_outer_position(PhysicalPosition::new(x, y));
+}
+
+fn load_icon(path: &Path) -> Option<Icon> {
+    let image = match image::open(path) {
+        Ok(image) => image.to_rgba8(),
+        Err(error) => {
+            eprintln!("Failed to load window icon {}: {error}", path.display());
+            return None;
+        }
+    };
+
+    let (width, height) = image.dimensions();
+
+    match Icon::from_rgba(image.into_raw(), width, height) {
+        Ok(icon) => Some(icon),
+        Err(error) => {
+            eprintln!("Failed to load window icon {}: {error}", path.display());
+            None
+        }
+    }
+}
@@ -2,9 +2,57 @@ declare module "rn-wgpu:rect" {
   // deno-lint-ignore no-explicit-any
   export type TODO_TAFFY_STYLE_TYPE_DEFS = any;
 
-  export function create_instance(style: TODO_TAFFY_STYLE_TYPE_DEFS): number;
+  export function create_instance(
+    style: TODO_TAFFY_STYLE_TYPE_DEFS,
+    backgroundColor: string,
+    borderRadius: TODO_TAFFY_STYLE_TYPE_DEFS,
+    borderColors: [string, string, string, string],
+    borderStyle: string
+  ): number;
+  export function update_instance(
+    rectId: number,
+    style: TODO_TAFFY_STYLE_TYPE_DEFS,
+    backgroundColor: string,
+    borderRadius: TODO_TAFFY_STYLE_TYPE_DEFS,
+    borderColors: [string, string, string, string],
+    borderStyle: string
+  ): void;
+  export function set_text(rectId: number, text: string, color: string, fontSize: number, lineHeight: number): void;
+  export function reset_text(rectId: number): void;
+  export function set_image(rectId: number, src: string, objectFit: string): void;
+  export function set_box_shadows(
+    rectId: number,
+    boxShadows: { offset_x: number; offset_y: number; blur: number; spread: number; color: string; inset: boolean }[]
+  ): void;
+  export function set_background_gradients(rectId: number, gradients: TODO_TAFFY_STYLE_TYPE_DEFS[]): void;
+  export function set_transform(
+    rectId: number,
+    transform: TODO_TAFFY_STYLE_TYPE_DEFS[],
+    transformOrigin: TODO_TAFFY_STYLE_TYPE_DEFS
+  ): void;
+  export function set_opacity(rectId: number, opacity: number): void;
+  export function set_z_index(rectId: number, zIndex: number | null): void;
+  export function scroll_to(rectId: number, left?: number, top?: number): [number, number] | null;
+  export function set_focusable(rectId: number, focusable: boolean): void;
   export function append_child_to_container(rectId: number): void;
   export function append_child(parentId: number, childId: number, index?: number): void;
+  export function insert_in_container_before(childId: number, beforeId: number): void;
+  export function insert_before(parentId: number, childId: number, beforeId: number): void;
+  export function remove_child_from_container(rectId: number): void;
+  export function remove_child(parentId: number, childId: number): void;
+  export function request_animation_frame(): void;
+  export function get_pixel_ratio(): number;
+  export function open_window(options: TODO_TAFFY_STYLE_TYPE_DEFS): number;
+  export function set_window_options(rootId: number, options: TODO_TAFFY_STYLE_TYPE_DEFS): void;
+  export function set_main_window_options(options: TODO_TAFFY_STYLE_TYPE_DEFS): void;
+  export function close_window(rootId: number): void;
+  export function start_animation(
+    rectId: number,
+    to: TODO_TAFFY_STYLE_TYPE_DEFS,
+    timing: TODO_TAFFY_STYLE_TYPE_DEFS
+  ): number | null;
+  export function stop_animation(animationId: number): void;
+  export function set_transitions(rectId: number, transitions: TODO_TAFFY_STYLE_TYPE_DEFS[]): void;
   export function get_style_defaults(): unknown;
   export function debug(): void;
 }